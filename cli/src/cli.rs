@@ -1,17 +1,205 @@
-use clap::Parser;
+use std::path::PathBuf;
+use clap::{Parser, Subcommand, ValueEnum};
+use driver::{BrightnessCurve, Chipset, Color, ColorOrder, DEFAULT_FPS, Easing, EffectKind, Rgb, WhiteBalance, WhiteExtraction};
 
+/// Colors can be given as hex like `#ff8800`, CSS names like `orange`,
+/// `hsv(30, 100%, 100%)` or color temperatures like `2700K`.
+/// The strip is read from the daemon's config, unless overridden by the options.
+/// While the daemon runs, commands go through it so its state stays current.
 #[derive(Parser, Debug)]
+#[clap(arg_required_else_help = true)]
 pub struct Cli {
-    #[clap(long, short)]
+    #[clap(subcommand)]
+    pub command: Command,
+    /// The daemon's config to read the strip from. Defaults to `/etc/deskled/config.toml` if it exists.
+    #[clap(long, global = true)]
+    pub config: Option<PathBuf>,
+    /// The strip to use, for configs with more than one in `[[strips]]`
+    #[clap(long, global = true)]
+    pub strip: Option<String>,
+    /// The address of the daemon's control API. Defaults to `[control] address` in the config, or `127.0.0.1:8081`.
+    #[clap(long, global = true)]
+    pub daemon: Option<String>,
+    /// Write to the strip directly, even if the daemon is running
+    #[clap(long, global = true)]
+    pub direct: bool,
+    /// Draw the strip in the terminal instead of writing to an SPI device
+    #[clap(long, global = true)]
+    pub simulate: bool,
+    /// Record the frames written to the strip to a file instead of showing them.
+    /// The file is replaced if it exists.
+    #[clap(long, global = true)]
+    pub record: Option<PathBuf>,
+    /// The SPI device, either a path like `/dev/spidev0.0` or bus and chip select like `0.0`
+    #[clap(long, short, global = true)]
     pub dev: Option<String>,
-    #[clap(long, short)]
-    pub length: u16,
-    #[clap(long, short)]
-    pub red: Option<u8>,
-    #[clap(long, short)]
-    pub green: Option<u8>,
-    #[clap(long, short)]
-    pub blue: Option<u8>,
+    /// The SPI clock speed in Hz
+    #[clap(long, global = true)]
+    pub speed_hz: Option<u32>,
+    /// The number of LEDs on the strip
+    #[clap(long, short, global = true)]
+    pub length: Option<u16>,
+    /// The LED controller used by the strip: ws28xx, apa102, sk9822, ws2801 or lpd8806
+    #[clap(long, global = true)]
+    pub chipset: Option<Chipset>,
+    /// The order in which the strip expects the color channels, e.g. `grb`
+    #[clap(long, global = true)]
+    pub color_order: Option<ColorOrder>,
+    /// Drive an RGBW strip, deriving the white channel with `none`, `min`
+    /// or the temperature of the white LED, e.g. `4500K`
+    #[clap(long, global = true)]
+    pub rgbw: Option<WhiteExtraction>,
+    /// Gamma exponent applied to each channel
+    #[clap(long, global = true)]
+    pub gamma: Option<f32>,
+    /// Scale factors for the red, green and blue channels, e.g. `1.0,0.85,0.7`
+    #[clap(long, global = true)]
+    pub white_balance: Option<WhiteBalance>,
+    /// How the brightness percentage maps to LED output, `linear` or `cie`
+    #[clap(long, global = true)]
+    pub brightness_curve: Option<BrightnessCurve>,
+    /// The maximum current the strip may draw, in mA
+    #[clap(long, global = true)]
+    pub max_milliamps: Option<u32>,
+    /// Current drawn by one color channel of one LED at full brightness, in mA
+    #[clap(long, global = true)]
+    pub milliamps_per_channel: Option<f32>,
+    /// Dither colors too dim for 8 bits. Keeps refreshing the strip until interrupted.
+    #[clap(long, global = true)]
+    pub dither: bool,
+    /// Brightness in percent, e.g. `40` or `40%`. Defaults to 100,
+    /// or the brightness the daemon has stored when going through it.
+    #[clap(long, short = 'B', global = true, value_parser = parse_percent)]
+    pub brightness: Option<u8>,
+    /// The frame rate fades and effects are rendered at
+    #[clap(long, global = true, default_value_t = DEFAULT_FPS)]
+    pub fps: u32,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Set the whole strip to a color
+    Set {
+        color: Color,
+    },
+    /// Turn the strip off
+    Off,
+    /// Show the state stored by the daemon
+    Status,
+    /// Set a range of LEDs to a color, turning the others off
+    Fill {
+        color: Color,
+        /// The first LED of the range
+        #[clap(long, default_value_t = 0)]
+        start: u16,
+        /// The number of LEDs in the range. Runs to the end of the strip if not set.
+        #[clap(long)]
+        count: Option<u16>,
+    },
+    /// Spread a gradient through two or more colors across the strip
+    Gradient {
+        #[clap(required = true, min_values = 2)]
+        colors: Vec<Color>,
+    },
+    /// Fade from one color to another
+    Fade {
+        /// The color to fade to
+        color: Color,
+        /// The color to fade from. Fades in from off if not set.
+        #[clap(long)]
+        from: Option<Color>,
+        /// How long the fade takes, in milliseconds
+        #[clap(long, default_value_t = 1000)]
+        duration_ms: u64,
+        /// How the fade progresses: linear, ease-in, ease-out or ease-in-out
+        #[clap(long, default_value_t)]
+        easing: Easing,
+    },
+    /// Run an effect: rainbow, breathe, chase, twinkle, fire or color-wipe
+    Effect {
+        kind: EffectKind,
+        /// Multiplier for how fast the effect runs
+        #[clap(long, default_value_t = 1.0)]
+        speed: f32,
+        /// How bright the effect is, from 0.0 to 1.0
+        #[clap(long, default_value_t = 1.0)]
+        intensity: f32,
+        /// Comma separated colors used by the effect, e.g. `orange,#0088ff`
+        #[clap(long, value_delimiter = ',')]
+        palette: Vec<Rgb>,
+        /// Seconds to run the effect for. Runs until interrupted if not set.
+        #[clap(long)]
+        duration: Option<u64>,
+    },
+    /// Write frames read from stdin to the strip as they arrive, e.g. from a music visualizer
+    Stream {
+        /// `raw` for 3 bytes per LED: red, green and blue.
+        /// `hex` for a line per frame of whitespace separated colors, one per LED, like `ff0000 00ff00 0000ff`.
+        #[clap(long, value_enum, default_value = "raw")]
+        format: StreamFormat,
+        /// Write at most this many frames per second. Frames arriving faster are held back.
+        #[clap(long)]
+        max_fps: Option<u32>,
+    },
+    /// Adjust the color interactively, with a live preview.
+    /// With `--simulate`, the strip is only previewed.
+    Tui,
+    /// List the available SPI devices
+    ListDevices,
+    /// Play a recording on the strip
+    Play {
+        /// The recording to play
+        file: PathBuf,
+        /// Multiplier for the playback speed, e.g. 2.0 plays twice as fast
+        #[clap(long, default_value_t = 1.0)]
+        rate: f32,
+    },
+    /// Show test patterns, to check the wiring and configuration of a new strip
+    Test {
+        #[clap(subcommand)]
+        pattern: TestPattern,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TestPattern {
+    /// Light one LED at a time, walking from the controller to the end of the strip until interrupted
+    Walk {
+        /// The color of the lit LED
+        #[clap(long, default_value = "#ffffff")]
+        color: Rgb,
+        /// How long each LED stays lit, in milliseconds
+        #[clap(long, default_value_t = 200)]
+        interval_ms: u64,
+    },
+    /// Light every Nth LED, to count the real length of the strip.
+    /// The first LED is green, the last blue and the markers in between red.
+    Markers {
+        /// The distance between markers
+        #[clap(long, short = 'n', default_value_t = 10)]
+        every: u16,
+    },
+    /// Cycle the whole strip through pure red, green and blue until interrupted, to find its color order
+    Channels {
+        /// How long each color is shown, in milliseconds
+        #[clap(long, default_value_t = 2000)]
+        interval_ms: u64,
+    },
+    /// Light the whole strip white, to check the power supply and wiring under load
+    BurnIn {
+        /// Brightness in percent
+        #[clap(default_value_t = 100)]
+        level: u8,
+        /// Seconds after which the strip is turned off again. Stays on if not set.
+        #[clap(long)]
+        duration: Option<u64>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum StreamFormat {
+    Raw,
+    Hex,
 }
 
 impl Cli {
@@ -19,3 +207,67 @@ impl Cli {
         Self::parse()
     }
 }
+
+/// Parses a percentage from 0 to 100, with or without a `%` sign
+fn parse_percent(s: &str) -> Result<u8, String> {
+    s.trim_end_matches('%')
+        .parse::<u8>()
+        .ok()
+        .filter(|x| *x <= 100)
+        .ok_or_else(|| format!("'{s}' is not a percentage from 0 to 100"))
+}
+
+#[cfg(test)]
+mod test {
+    use clap::{CommandFactory, Parser};
+    use driver::{Color, Rgb};
+    use crate::cli::{Cli, Command, parse_percent};
+
+    fn set(args: &[&str]) -> Result<(Color, Option<u8>), clap::Error> {
+        let cli = Cli::try_parse_from(["cli", "set"].iter().chain(args))?;
+        match cli.command {
+            Command::Set { color } => Ok((color, cli.brightness)),
+            command => panic!("Parsed {command:?} instead of set"),
+        }
+    }
+
+    #[test]
+    fn test_arguments() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(Ok(0), parse_percent("0"));
+        assert_eq!(Ok(40), parse_percent("40"));
+        assert_eq!(Ok(100), parse_percent("100%"));
+        assert!(parse_percent("101").is_err());
+        assert!(parse_percent("-1").is_err());
+        assert!(parse_percent("40.5").is_err());
+        assert!(parse_percent("").is_err());
+
+        assert_eq!(Some(40), set(&["red", "-B", "40%"]).unwrap().1);
+        assert!(set(&["red", "--brightness", "150"]).is_err());
+    }
+
+    #[test]
+    fn test_kelvin() {
+        assert_eq!(Color::Kelvin(2700), set(&["2700K"]).unwrap().0);
+        assert_eq!(Color::Kelvin(6500), set(&["6500k"]).unwrap().0);
+        // Too warm or cold for a temperature, and not a color either
+        assert!(set(&["70000K"]).is_err());
+        assert!(set(&["-2700K"]).is_err());
+        // Not a temperature, even though it ends in a k
+        assert_eq!(Color::Rgb(Rgb::new(0, 0, 0)), set(&["black"]).unwrap().0);
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(Color::Rgb(Rgb::new(255, 136, 0)), set(&["#ff8800"]).unwrap().0);
+        assert_eq!(Color::Rgb(Rgb::new(255, 136, 0)), set(&["FF8800"]).unwrap().0);
+        assert_eq!(Color::Rgb(Rgb::new_rgbw(255, 136, 0, 64)), set(&["#ff880040"]).unwrap().0);
+        assert!(set(&["#ff880"]).is_err());
+        assert!(set(&["#ff8800400"]).is_err());
+        assert!(set(&["#gg8800"]).is_err());
+    }
+}
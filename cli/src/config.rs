@@ -0,0 +1,129 @@
+use std::fs;
+use std::path::Path;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+use driver::{Chipset, ColorOrder, Correction, KelvinCalibration, Layout, NetworkProtocol, WhiteExtraction};
+
+/// Where the daemon reads its config from when installed
+pub const DEFAULT_PATH: &str = "/etc/deskled/config.toml";
+/// Where the daemon's control API listens unless configured otherwise
+pub const DEFAULT_CONTROL_ADDRESS: &str = "127.0.0.1:8081";
+
+/// The parts of the daemon's config the CLI needs. Everything else in it is ignored.
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    led: Strip,
+    #[serde(default)]
+    strips: Vec<Strip>,
+    #[serde(default)]
+    control: Control,
+}
+
+/// A strip as configured in `[led]` or `[[strips]]`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Strip {
+    #[serde(default)]
+    pub name: String,
+    pub length: Option<u16>,
+    pub device: Option<String>,
+    pub spi_speed_hz: Option<u32>,
+    pub chipset: Option<Chipset>,
+    pub color_order: Option<ColorOrder>,
+    pub white: Option<WhiteExtraction>,
+    /// Drive the strip through a controller on the network instead of SPI
+    pub remote: Option<Remote>,
+    #[serde(default)]
+    pub layout: Layout,
+    #[serde(default)]
+    pub kelvin: KelvinCalibration,
+    /// Only read from `[led]`, it applies to every strip
+    #[serde(default)]
+    pub correction: Correction,
+}
+
+/// A controller on the network, like WLED
+#[derive(Debug, Clone, Deserialize)]
+pub struct Remote {
+    pub protocol: NetworkProtocol,
+    pub address: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Control {
+    address: Option<String>,
+}
+
+impl Config {
+    /// Read the daemon's config at `path`, or at [DEFAULT_PATH] if it exists.
+    /// Without a config, nothing is configured.
+    pub fn read(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(x) => x,
+            None if Path::new(DEFAULT_PATH).exists() => Path::new(DEFAULT_PATH),
+            None => return Ok(Self::default()),
+        };
+
+        let contents = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_slice(&contents).with_context(|| format!("Invalid config {}", path.display()))
+    }
+
+    /// The strip named `name` in `[[strips]]`, which is required if there is more than one.
+    /// Without any, the strip configured in `[led]`.
+    pub fn strip(&self, name: Option<&str>) -> Result<Strip> {
+        if self.strips.is_empty() {
+            return match name {
+                Some(name) => Err(anyhow!("No strip named '{name}' in the config")),
+                None => Ok(self.led.clone()),
+            };
+        }
+
+        let names = || self.strips.iter().map(|x| x.name.as_str()).collect::<Vec<_>>().join(", ");
+        let strip = match name {
+            Some(name) => self.strips.iter()
+                .find(|x| x.name == name)
+                .ok_or_else(|| anyhow!("No strip named '{name}', expected one of {}", names()))?,
+            None if self.strips.len() == 1 => &self.strips[0],
+            None => bail!("The config has more than one strip, pick one of {} with --strip", names()),
+        };
+
+        Ok(Strip {
+            correction: self.led.correction,
+            ..strip.clone()
+        })
+    }
+
+    /// The address of the daemon's control API
+    pub fn control_address(&self) -> &str {
+        self.control.address.as_deref().unwrap_or(DEFAULT_CONTROL_ADDRESS)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use driver::NetworkProtocol;
+    use crate::config::Config;
+
+    #[test]
+    fn test_strips_share_led_settings() {
+        let config: Config = toml::from_str(r#"
+            [led]
+            length = 10
+
+            [led.correction]
+            gamma = 2.2
+
+            [[strips]]
+            name = 'desk'
+            length = 30
+            remote = { protocol = 'ddp', address = '192.168.1.50' }
+            layout = { reversed = true }
+        "#).unwrap();
+
+        let strip = config.strip(Some("desk")).unwrap();
+        assert_eq!(Some(30), strip.length);
+        assert_eq!(2.2, strip.correction.gamma);
+        assert!(strip.layout.reversed);
+        assert_eq!(NetworkProtocol::Ddp, strip.remote.unwrap().protocol);
+    }
+}
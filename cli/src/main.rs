@@ -1,61 +1,378 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
 use std::process::exit;
-use tracing::{debug, error, info};
-use driver::{Driver, Rgb};
-use crate::cli::Cli;
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, error, info, Level};
+use driver::{Color, Correction, Driver, EffectKind, EffectParams, Engine, Frame, NetworkOutput, Output, PixelFormat, PowerBudget, RecorderOutput, RecordingReader, Rgb, SpiOutput, Spidev, TerminalOutput, Transition};
+use crate::cli::{Cli, Command};
+use crate::config::{Config, Strip};
+use crate::remote::Daemon;
 
 mod cli;
+mod config;
+mod patterns;
+mod remote;
+mod stream;
+mod tui;
 
 fn main() {
-    setup_tracing();
+    let cli = Cli::new();
+    // Log lines would break up the simulated strip and the TUI
+    let quiet = cli.simulate || matches!(cli.command, Command::Tui);
+    setup_tracing(if quiet { Level::WARN } else { Level::TRACE });
     info!("Welcome! v{}", env!("CARGO_PKG_VERSION"));
 
-    debug!("Parsing CLI");
-    let cli = Cli::new();
+    if let Command::ListDevices = cli.command {
+        list_devices();
+        return;
+    }
+
+    let config = match Config::read(cli.config.as_deref()) {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to read config: {e:#}");
+            exit(1);
+        }
+    };
+
+    if let Some(daemon) = connect_daemon(&cli, &config) {
+        if let Err(e) = remote::run(&cli, &daemon) {
+            error!("{e:#}");
+            exit(1);
+        }
+        return;
+    }
+
+    if let Command::Status = cli.command {
+        error!("The daemon isn't running");
+        exit(1);
+    }
+
+    let strip = read_strip(&cli, &config);
+    if let Command::Tui = cli.command {
+        let target = match cli.simulate {
+            true => tui::Target::Preview,
+            false => tui::Target::Strip(Engine::spawn(open_driver(&cli, &strip), cli.fps)),
+        };
+        if let Err(e) = tui::run(target, cli.brightness.unwrap_or(100)) {
+            error!("{e:#}");
+            exit(1);
+        }
+        return;
+    }
+
+    if let Command::Play { file, rate } = &cli.command {
+        play_recording(&cli, &strip, file, *rate);
+        return;
+    }
+
+    let mut driver = open_driver(&cli, &strip);
+    let length = driver.length();
+    let frame = match &cli.command {
+        Command::Set { color } => Frame::filled(length, driver.color_to_rgb(*color)),
+        Command::Off => Frame::new(length),
+        Command::Fill { color, start, count } => {
+            let rgb = driver.color_to_rgb(*color);
+            let mut frame = Frame::new(length);
+            let end = count.map_or(length, |x| start.saturating_add(x).min(length));
+            for i in *start..end {
+                frame.set(i as usize, rgb);
+            }
+            frame
+        },
+        Command::Gradient { colors } => {
+            let colors = colors.iter().map(|x| driver.color_to_rgb(*x)).collect::<Vec<_>>();
+            Frame::gradient(length, &colors)
+        },
+        Command::Fade { color, from, duration_ms, easing } => {
+            let transition = Transition { duration_ms: *duration_ms, easing: *easing };
+            fade(&cli, driver, *from, *color, transition);
+            return;
+        },
+        Command::Effect { kind, speed, intensity, palette, duration } => {
+            let params = EffectParams {
+                speed: *speed,
+                palette: palette.clone(),
+                intensity: *intensity,
+            };
+            run_effect(&cli, driver, *kind, &params, duration.map(Duration::from_secs));
+            return;
+        },
+        Command::Test { pattern } => {
+            if let Err(e) = patterns::run(&mut driver, pattern, strip.white.is_some()) {
+                error!("Failed to show test pattern: {e}");
+                exit(1);
+            }
+            return;
+        },
+        Command::Stream { format, max_fps } => {
+            if let Err(e) = stream::run(&mut driver, *format, *max_fps) {
+                error!("Failed to stream: {e:#}");
+                exit(1);
+            }
+            return;
+        },
+        Command::ListDevices | Command::Status | Command::Play { .. } | Command::Tui => unreachable!("Handled above"),
+    };
+
+    show(&cli, driver, frame);
+}
+
+/// The daemon, unless it isn't running or the strip isn't to be written to through it
+fn connect_daemon(cli: &Cli, config: &Config) -> Option<Daemon> {
+    if cli.direct || cli.simulate || cli.record.is_some() {
+        return None;
+    }
+
+    let address = cli.daemon.as_deref().unwrap_or_else(|| config.control_address());
+    match Daemon::connect(address, cli.strip.as_deref()) {
+        Ok(Some(x)) => {
+            debug!("Going through the daemon at {address}");
+            Some(x)
+        },
+        Ok(None) => {
+            debug!("No daemon running at {address}, writing to the strip directly");
+            None
+        },
+        Err(e) => {
+            error!("{e:#}");
+            exit(1);
+        }
+    }
+}
+
+/// The strip from the daemon's config, with the options given on the command line taking precedence
+fn read_strip(cli: &Cli, config: &Config) -> Strip {
+    let strip = match config.strip(cli.strip.as_deref()) {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to read config: {e:#}");
+            exit(1);
+        }
+    };
+
+    Strip {
+        length: cli.length.or(strip.length),
+        device: cli.dev.clone().or(strip.device),
+        spi_speed_hz: cli.speed_hz.or(strip.spi_speed_hz),
+        chipset: cli.chipset.or(strip.chipset),
+        color_order: cli.color_order.or(strip.color_order),
+        white: cli.rgbw.or(strip.white),
+        // A device given on the command line is on SPI, not on the network
+        remote: if cli.dev.is_some() { None } else { strip.remote },
+        ..strip
+    }
+}
+
+/// The driver for the strip, set up with the options given on the command line
+fn open_driver(cli: &Cli, strip: &Strip) -> Driver {
+    let length = match strip.length {
+        Some(x) => x,
+        None => {
+            error!("The length of the strip is required, either with --length or in the config");
+            exit(1);
+        }
+    };
+
+    let mut driver = Driver::with_output(open_output(cli, strip), length);
+
+    if let Err(e) = driver.set_layout(strip.layout.clone()) {
+        error!("Invalid layout: {e}");
+        exit(1);
+    }
+
+    let configured = strip.correction;
+    driver.set_correction(Correction {
+        gamma: cli.gamma.unwrap_or(configured.gamma),
+        white_balance: cli.white_balance.unwrap_or(configured.white_balance),
+        brightness_curve: cli.brightness_curve.unwrap_or(configured.brightness_curve),
+    });
+    driver.set_brightness(cli.brightness.unwrap_or(100) as f32 / 100.0);
+    driver.set_dithering(cli.dither);
+    if let Err(e) = strip.kelvin.validate(strip.white.is_some()) {
+        error!("Invalid kelvin calibration: {e}");
+        exit(1);
+    }
+    driver.set_kelvin_calibration(strip.kelvin.clone());
+
+    let defaults = PowerBudget::default();
+    driver.set_power_budget(PowerBudget {
+        milliamps_per_channel: cli.milliamps_per_channel.unwrap_or(defaults.milliamps_per_channel),
+        max_milliamps: cli.max_milliamps,
+        ..defaults
+    });
+
+    driver
+}
+
+/// Write a frame to the strip. When dithering, the strip keeps being refreshed until interrupted.
+fn show(cli: &Cli, mut driver: Driver, frame: Frame) {
+    if cli.dither {
+        let engine = Engine::spawn(driver, cli.fps);
+        if let Err(e) = engine.write_frame(frame).and_then(|_| engine.flush()) {
+            error!("Failed to write frame: {e}");
+            exit(1);
+        }
+
+        loop {
+            thread::park();
+        }
+    }
+
+    debug!("Writing frame");
+    match driver.write_frame(&frame) {
+        Ok(_) => {
+            let power = driver.power();
+            info!("Estimated current draw: {:.0} mA (requested {:.0} mA)", power.milliamps, power.requested_milliamps);
+        },
+        Err(e) => {
+            error!("Failed to write frame: {e}");
+            exit(1);
+        }
+    }
+}
+
+fn fade(cli: &Cli, driver: Driver, from: Option<Color>, to: Color, transition: Transition) {
+    debug!("Fading from {from:?} to {to}");
+    let engine = Engine::spawn(driver, cli.fps);
+    let result = engine.set_transition(Transition::NONE)
+        .and_then(|_| engine.set_color(from.unwrap_or(Color::Rgb(Rgb::off()))))
+        .and_then(|_| engine.wait_for_transition())
+        .and_then(|_| engine.set_transition(transition))
+        .and_then(|_| engine.set_color(to))
+        .and_then(|_| engine.wait_for_transition())
+        .and_then(|_| engine.flush());
+    if let Err(e) = result {
+        error!("Failed to fade: {e}");
+        exit(1);
+    }
+
+    // Dithering only works while the strip keeps being refreshed
+    if cli.dither {
+        loop {
+            thread::park();
+        }
+    }
+}
+
+fn run_effect(cli: &Cli, driver: Driver, kind: EffectKind, params: &EffectParams, duration: Option<Duration>) {
+    let engine = Engine::spawn(driver, cli.fps);
+
+    info!("Running effect {kind}");
+    if let Err(e) = engine.start_effect(kind, params) {
+        error!("Failed to start effect: {e}");
+        exit(1);
+    }
+
+    match duration {
+        Some(duration) => thread::sleep(duration),
+        None => loop {
+            thread::park();
+        }
+    }
+}
+
+/// The strip, the terminal when simulating, or a recording
+fn open_output(cli: &Cli, strip: &Strip) -> Box<dyn Output> {
+    if let Some(file) = &cli.record {
+        return match RecorderOutput::create(file, strip.white.is_some()) {
+            Ok(x) => Box::new(x),
+            Err(e) => {
+                error!("Failed to create recording: {e}");
+                exit(1);
+            }
+        };
+    }
+
+    if cli.simulate {
+        return Box::new(TerminalOutput::stdout());
+    }
+
+    if let Some(remote) = &strip.remote {
+        debug!("Sending to {} over {}", remote.address, remote.protocol);
+        return match NetworkOutput::new(&remote.address, remote.protocol, strip.white) {
+            Ok(x) => Box::new(x),
+            Err(e) => {
+                error!("Failed to reach the controller at {}: {e}", remote.address);
+                exit(1);
+            }
+        };
+    }
 
     debug!("Aquiring SPI device");
-    let spidev = match if let Some(spidev) = cli.dev {
-        driver::Spidev::new_with_name(spidev)
+    let spidev = match if let Some(spidev) = &strip.device {
+        Spidev::find(spidev)
     } else {
-        driver::Spidev::new()
+        Spidev::new()
     } {
-        Ok(x) => x,
+        Ok(x) => x.with_speed(strip.spi_speed_hz.unwrap_or(Spidev::DEFAULT_SPEED_HZ)),
         Err(e) => {
             error!("Failed to aquire SPI device. Is SPI enabled?: {e}");
             exit(1);
         }
     };
 
-    debug!("Creating Driver");
-    let mut driver = match Driver::new(&spidev, cli.length) {
+    debug!("Opening SPI device");
+    let format = PixelFormat {
+        chipset: strip.chipset.unwrap_or_default(),
+        color_order: strip.color_order.unwrap_or_default(),
+        white: strip.white,
+    };
+    match SpiOutput::new(&spidev, format) {
+        Ok(x) => Box::new(x),
+        Err(e) => {
+            error!("Failed to open SPI device: {e}");
+            exit(1);
+        }
+    }
+}
+
+fn play_recording(cli: &Cli, strip: &Strip, file: &Path, rate: f32) {
+    let mut recording = match File::open(file).map_err(driver::Error::from).and_then(|x| RecordingReader::new(BufReader::new(x))) {
         Ok(x) => x,
         Err(e) => {
-            error!("Failed to create driver: {e}");
+            error!("Failed to open recording: {e}");
             exit(1);
         }
     };
 
-    let rgb = Rgb::new(
-        cli.red.unwrap_or(0),
-        cli.green.unwrap_or(0),
-        cli.blue.unwrap_or(0)
-    );
+    let header = recording.header();
+    info!("Playing recording of {} LEDs", header.length);
+    let mut output = open_output(cli, strip);
+    if let Err(e) = driver::play(&mut recording, output.as_mut(), rate) {
+        error!("Failed to play recording: {e}");
+        exit(1);
+    }
+}
 
-    debug!("Setting RGB");
-    match driver.set_rgb(rgb) {
-        Ok(_) => {},
+fn list_devices() {
+    let devices = match Spidev::list() {
+        Ok(x) => x,
         Err(e) => {
-            error!("Failed to set RGB: {e}");
+            error!("Failed to list SPI devices: {e}");
             exit(1);
         }
+    };
+
+    if devices.is_empty() {
+        println!("No SPI devices found. Is SPI enabled?");
+    }
+
+    for device in devices {
+        match (device.bus, device.chip_select) {
+            (Some(bus), Some(cs)) => println!("{} (bus {bus}, chip select {cs})", device.path.display()),
+            _ => println!("{}", device.path.display()),
+        }
     }
 }
 
-
-pub fn setup_tracing() {
+pub fn setup_tracing(level: Level) {
     let sub = tracing_subscriber::fmt()
         .compact()
-        .with_max_level(tracing::Level::TRACE)
+        .with_max_level(level)
         .finish();
 
     tracing::subscriber::set_global_default(sub).expect("Setting tracing subscriber");
-}
\ No newline at end of file
+}
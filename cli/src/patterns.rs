@@ -0,0 +1,164 @@
+use std::thread;
+use std::time::Duration;
+use tracing::info;
+use driver::{BrightnessCurve, Correction, Driver, Frame, Result, Rgb};
+use crate::cli::TestPattern;
+
+/// Show a test pattern on the strip. `rgbw` includes the white channel in the pattern.
+pub fn run(driver: &mut Driver, pattern: &TestPattern, rgbw: bool) -> Result<()> {
+    match pattern {
+        TestPattern::Walk { color, interval_ms } => walk(driver, *color, Duration::from_millis(*interval_ms)),
+        TestPattern::Markers { every } => markers(driver, *every),
+        TestPattern::Channels { interval_ms } => channels(driver, rgbw, Duration::from_millis(*interval_ms)),
+        TestPattern::BurnIn { level, duration } => burn_in(driver, *level, rgbw, duration.map(Duration::from_secs)),
+    }
+}
+
+fn walk(driver: &mut Driver, color: Rgb, interval: Duration) -> Result<()> {
+    loop {
+        walk_once(driver, color, interval)?;
+    }
+}
+
+/// Light up every LED in turn, once
+fn walk_once(driver: &mut Driver, color: Rgb, interval: Duration) -> Result<()> {
+    let length = driver.length();
+    let mut frame = Frame::new(length);
+    for i in 0..length as usize {
+        frame.fill(Rgb::off());
+        frame.set(i, color);
+        info!("LED {i}");
+        driver.write_frame(&frame)?;
+        thread::sleep(interval);
+    }
+
+    Ok(())
+}
+
+fn markers(driver: &mut Driver, every: u16) -> Result<()> {
+    let length = driver.length();
+    let every = every.max(1);
+    let mut frame = Frame::new(length);
+    for i in (0..length).step_by(every as usize) {
+        frame.set(i as usize, Rgb::new(255, 0, 0));
+    }
+    frame.set(0, Rgb::new(0, 255, 0));
+    frame.set(length.saturating_sub(1) as usize, Rgb::new(0, 0, 255));
+
+    info!("LED 0 is green, LED {} is blue, and every {every}th LED in between is red", length.saturating_sub(1));
+    info!("If the blue LED isn't the last one on the strip, the length of {length} is wrong");
+    driver.write_frame(&frame)
+}
+
+fn channels(driver: &mut Driver, rgbw: bool, interval: Duration) -> Result<()> {
+    info!("If the strip shows a different color than the one logged, adjust --color-order");
+    loop {
+        channels_once(driver, rgbw, interval)?;
+    }
+}
+
+/// Show every channel in turn, once
+fn channels_once(driver: &mut Driver, rgbw: bool, interval: Duration) -> Result<()> {
+    let mut colors = vec![
+        ("red", Rgb::new(255, 0, 0)),
+        ("green", Rgb::new(0, 255, 0)),
+        ("blue", Rgb::new(0, 0, 255)),
+    ];
+    if rgbw {
+        colors.push(("white", Rgb::new_rgbw(0, 0, 0, 255)));
+    }
+
+    for (name, rgb) in &colors {
+        info!("Showing {name}");
+        driver.set_rgb(*rgb)?;
+        thread::sleep(interval);
+    }
+
+    Ok(())
+}
+
+fn burn_in(driver: &mut Driver, level: u8, rgbw: bool, duration: Option<Duration>) -> Result<()> {
+    // The level is the duty cycle the power supply is tested at, not how bright it looks
+    driver.set_correction(Correction {
+        brightness_curve: BrightnessCurve::Linear,
+        ..*driver.correction()
+    });
+    driver.set_brightness(level as f32 / 100.0);
+    let white = if rgbw { 255 } else { 0 };
+    driver.set_rgb(Rgb::new_rgbw(255, 255, 255, white))?;
+
+    let power = driver.power();
+    info!("Burning in at {level}%, estimated current draw: {:.0} mA (requested {:.0} mA)", power.milliamps, power.requested_milliamps);
+
+    if let Some(duration) = duration {
+        thread::sleep(duration);
+        info!("Turning the strip off");
+        driver.set_rgb(Rgb::off())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use driver::{Driver, MemoryOutput, Rgb};
+    use crate::patterns::{burn_in, channels_once, markers, walk_once};
+
+    fn driver(length: u16) -> (Driver, MemoryOutput) {
+        let output = MemoryOutput::new();
+        (Driver::with_output(Box::new(output.clone()), length), output)
+    }
+
+    #[test]
+    fn test_walk() {
+        let (mut driver, output) = driver(3);
+        let color = Rgb::new(0, 255, 0);
+        walk_once(&mut driver, color, Duration::ZERO).unwrap();
+
+        let frames = output.frames();
+        assert_eq!(3, frames.len());
+        for (i, frame) in frames.iter().enumerate() {
+            for (led, &rgb) in frame.pixels().iter().enumerate() {
+                assert_eq!(if led == i { color } else { Rgb::off() }, rgb);
+            }
+        }
+    }
+
+    #[test]
+    fn test_markers() {
+        let (mut driver, output) = driver(12);
+        markers(&mut driver, 5).unwrap();
+
+        let frame = output.last().unwrap();
+        assert_eq!(Rgb::new(0, 255, 0), frame[0]);
+        assert_eq!(Rgb::new(255, 0, 0), frame[5]);
+        assert_eq!(Rgb::new(255, 0, 0), frame[10]);
+        assert_eq!(Rgb::new(0, 0, 255), frame[11]);
+        assert_eq!(Rgb::off(), frame[1]);
+    }
+
+    #[test]
+    fn test_channels() {
+        let (mut driver, output) = driver(2);
+        channels_once(&mut driver, false, Duration::ZERO).unwrap();
+        let shown = output.frames().iter().map(|x| x[0]).collect::<Vec<_>>();
+        assert_eq!(vec![Rgb::new(255, 0, 0), Rgb::new(0, 255, 0), Rgb::new(0, 0, 255)], shown);
+
+        output.clear();
+        channels_once(&mut driver, true, Duration::ZERO).unwrap();
+        assert_eq!(Rgb::new_rgbw(0, 0, 0, 255), output.last().unwrap()[1]);
+    }
+
+    #[test]
+    fn test_burn_in_level_is_linear() {
+        let (mut driver, output) = driver(1);
+        burn_in(&mut driver, 50, false, None).unwrap();
+
+        // Half of full duty, not half as bright looking
+        let rgb = output.last().unwrap()[0];
+        assert!((127..=128).contains(&rgb.r), "{rgb:?}");
+        assert_eq!(rgb.r, rgb.g);
+        assert_eq!(rgb.r, rgb.b);
+    }
+}
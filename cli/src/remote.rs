@@ -0,0 +1,176 @@
+use std::thread;
+use std::time::Duration;
+use anyhow::{anyhow, bail, Result};
+use serde::{Serialize, Deserialize};
+use tracing::info;
+use ureq::{Agent, AgentBuilder};
+use driver::{Color, EffectKind, EffectParams, Transition};
+use crate::cli::{Cli, Command};
+use crate::tui::{self, Target};
+
+/// The daemon's name for all strips together
+const ALL_STRIPS: &str = "0";
+
+/// The control API of a running daemon. Changes made through it are stored
+/// and reported to Google, just like changes made through Google.
+pub struct Daemon {
+    agent: Agent,
+    /// The URL of the strip, or of all strips together
+    url: String,
+}
+
+/// The state of a strip, as stored by the daemon
+#[derive(Debug, Deserialize)]
+pub struct DeviceState {
+    pub on: bool,
+    /// In percent
+    pub brightness: u8,
+    pub color: Color,
+    pub effect: Option<EffectKind>,
+}
+
+/// A change to the state of a strip. Everything not set is left as it is.
+#[derive(Debug, Default, Serialize)]
+pub struct StateChange {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub brightness: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effect: Option<Effect>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stop_effect: bool,
+    /// Fade the change in with this transition rather than the daemon's own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transition: Option<Transition>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Effect {
+    pub kind: EffectKind,
+    #[serde(flatten)]
+    pub params: EffectParams,
+}
+
+impl Daemon {
+    /// Connect to the daemon listening at `address`, `None` if it isn't running.
+    /// `strip` is the name of the strip to control, all strips if not set.
+    pub fn connect(address: &str, strip: Option<&str>) -> Result<Option<Self>> {
+        let this = Self {
+            agent: AgentBuilder::new()
+                .timeout_connect(Duration::from_millis(500))
+                .build(),
+            url: format!("http://{address}/control/{}", strip.unwrap_or(ALL_STRIPS)),
+        };
+
+        match this.agent.get(&this.url).call() {
+            Ok(_) => Ok(Some(this)),
+            Err(ureq::Error::Transport(_)) => Ok(None),
+            Err(e) => Err(Self::error(e)),
+        }
+    }
+
+    pub fn state(&self) -> Result<DeviceState> {
+        let response = self.agent.get(&self.url).call().map_err(Self::error)?;
+        Ok(response.into_json()?)
+    }
+
+    /// Apply a change, returning the state after it.
+    /// A transition is left to finish in the daemon.
+    pub fn change(&self, change: &StateChange) -> Result<DeviceState> {
+        let response = self.agent.post(&self.url).send_json(change).map_err(Self::error)?;
+        Ok(response.into_json()?)
+    }
+
+    fn error(error: ureq::Error) -> anyhow::Error {
+        match error {
+            ureq::Error::Status(404, _) => anyhow!("The daemon has no such strip. Individual strips can only be picked if there is more than one"),
+            ureq::Error::Status(status, response) => {
+                let body = response.into_string().unwrap_or_default();
+                anyhow!("The daemon responded with {status}: {body}")
+            },
+            ureq::Error::Transport(e) => anyhow!("Failed to reach the daemon: {e}"),
+        }
+    }
+}
+
+/// Run a command through the daemon
+pub fn run(cli: &Cli, daemon: &Daemon) -> Result<()> {
+    match &cli.command {
+        Command::Status => {
+            let state = daemon.state()?;
+            match state.on {
+                true => println!("On at {}%, {}", state.brightness, state.color),
+                false => println!("Off, {} at {}% when turned on", state.color, state.brightness),
+            }
+            if let Some(effect) = state.effect {
+                println!("Running effect {effect}");
+            }
+        },
+        Command::Set { color } => {
+            daemon.change(&StateChange {
+                on: Some(true),
+                brightness: cli.brightness,
+                color: Some(*color),
+                ..StateChange::default()
+            })?;
+        },
+        Command::Off => {
+            daemon.change(&StateChange {
+                on: Some(false),
+                ..StateChange::default()
+            })?;
+        },
+        Command::Fade { color, from, duration_ms, easing } => {
+            if let Some(from) = from {
+                daemon.change(&StateChange {
+                    on: Some(true),
+                    color: Some(*from),
+                    transition: Some(Transition::NONE),
+                    ..StateChange::default()
+                })?;
+            }
+
+            daemon.change(&StateChange {
+                on: Some(true),
+                brightness: cli.brightness,
+                color: Some(*color),
+                transition: Some(Transition { duration_ms: *duration_ms, easing: *easing }),
+                ..StateChange::default()
+            })?;
+        },
+        Command::Effect { kind, speed, intensity, palette, duration } => {
+            info!("Starting effect {kind}");
+            daemon.change(&StateChange {
+                brightness: cli.brightness,
+                effect: Some(Effect {
+                    kind: *kind,
+                    params: EffectParams {
+                        speed: *speed,
+                        palette: palette.clone(),
+                        intensity: *intensity,
+                    },
+                }),
+                ..StateChange::default()
+            })?;
+
+            // Without a duration, the effect keeps running in the daemon
+            if let Some(duration) = duration {
+                thread::sleep(Duration::from_secs(*duration));
+                daemon.change(&StateChange {
+                    stop_effect: true,
+                    ..StateChange::default()
+                })?;
+            }
+        },
+        Command::Tui => tui::run(Target::Daemon(daemon), 100)?,
+        Command::Fill { .. } | Command::Gradient { .. } | Command::Test { .. } | Command::Play { .. } | Command::Stream { .. } => {
+            bail!("This can't go through the daemon, which only stores a single color. Stop the daemon or pass --direct");
+        },
+        Command::ListDevices => bail!("Listing devices doesn't need the daemon"),
+    }
+
+    Ok(())
+}
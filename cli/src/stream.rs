@@ -0,0 +1,142 @@
+use std::io::{self, BufRead, ErrorKind, Read};
+use std::thread;
+use std::time::{Duration, Instant};
+use anyhow::{bail, Context, Result};
+use tracing::debug;
+use driver::{Driver, Frame, Rgb};
+use crate::cli::StreamFormat;
+
+/// Write the frames read from stdin to the strip as they arrive, until stdin is closed.
+/// With `max_fps`, frames arriving faster are held back, which in turn slows down the writer.
+pub fn run(driver: &mut Driver, format: StreamFormat, max_fps: Option<u32>) -> Result<()> {
+    let length = driver.length();
+    let interval = max_fps.filter(|x| *x > 0).map(|x| Duration::from_secs_f64(1.0 / x as f64));
+
+    let stdin = io::stdin();
+    let mut stdin = stdin.lock();
+    let mut last_write: Option<Instant> = None;
+    let mut frames = 0usize;
+    loop {
+        let frame = match format {
+            StreamFormat::Raw => read_raw(&mut stdin, length)?,
+            StreamFormat::Hex => read_hex(&mut stdin, length)?,
+        };
+        let frame = match frame {
+            Some(x) => x,
+            None => break,
+        };
+
+        if let (Some(interval), Some(last_write)) = (interval, last_write) {
+            let elapsed = last_write.elapsed();
+            if elapsed < interval {
+                thread::sleep(interval - elapsed);
+            }
+        }
+
+        last_write = Some(Instant::now());
+        driver.write_frame(&frame)?;
+        frames += 1;
+    }
+
+    debug!("Stream ended after {frames} frames");
+    Ok(())
+}
+
+/// A frame of 3 bytes per LED, red, green and blue. `None` once the stream ends.
+fn read_raw(reader: &mut impl Read, length: u16) -> Result<Option<Frame>> {
+    let mut bytes = vec![0; length as usize * 3];
+    let mut read = 0;
+    while read < bytes.len() {
+        match reader.read(&mut bytes[read..]) {
+            Ok(0) if read == 0 => return Ok(None),
+            Ok(0) => bail!("The stream ended in the middle of a frame, after {read} of {} bytes", bytes.len()),
+            Ok(n) => read += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {},
+            Err(e) => return Err(e).context("Failed to read from stdin"),
+        }
+    }
+
+    let pixels = bytes.chunks_exact(3)
+        .map(|x| Rgb::new(x[0], x[1], x[2]))
+        .collect::<Vec<_>>();
+    Ok(Some(pixels.into()))
+}
+
+/// A frame from a line of whitespace separated colors, one per LED, like `ff0000 #00ff00 blue`.
+/// LEDs without a color are turned off, empty lines are skipped. `None` once the stream ends.
+fn read_hex(reader: &mut impl BufRead, length: u16) -> Result<Option<Frame>> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).context("Failed to read from stdin")? == 0 {
+            return Ok(None);
+        }
+        if !line.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut frame = Frame::new(length);
+    for (i, color) in line.split_whitespace().enumerate() {
+        if i >= length as usize {
+            bail!("A line has more than {length} colors, one for every LED");
+        }
+
+        let rgb = color.parse::<Rgb>().with_context(|| format!("Invalid color '{color}'"))?;
+        frame.set(i, rgb);
+    }
+
+    Ok(Some(frame))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+    use driver::Rgb;
+    use crate::stream::{read_hex, read_raw};
+
+    #[test]
+    fn test_raw() {
+        let mut reader = Cursor::new(vec![255, 0, 0, 0, 255, 0, 1, 2, 3, 4, 5, 6]);
+        let frame = read_raw(&mut reader, 2).unwrap().unwrap();
+        assert_eq!(&[Rgb::new(255, 0, 0), Rgb::new(0, 255, 0)], frame.pixels());
+        let frame = read_raw(&mut reader, 2).unwrap().unwrap();
+        assert_eq!(&[Rgb::new(1, 2, 3), Rgb::new(4, 5, 6)], frame.pixels());
+        assert!(read_raw(&mut reader, 2).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_raw_partial_frame() {
+        // One byte short of the second LED
+        let mut reader = Cursor::new(vec![255, 0, 0, 0, 255]);
+        assert!(read_raw(&mut reader, 2).is_err());
+    }
+
+    #[test]
+    fn test_hex() {
+        let mut reader = Cursor::new("ff0000 #00ff00 blue\n\n  \n000001\n");
+        let frame = read_hex(&mut reader, 3).unwrap().unwrap();
+        assert_eq!(&[Rgb::new(255, 0, 0), Rgb::new(0, 255, 0), Rgb::new(0, 0, 255)], frame.pixels());
+
+        // Empty lines are skipped, LEDs without a color are off
+        let frame = read_hex(&mut reader, 3).unwrap().unwrap();
+        assert_eq!(&[Rgb::new(0, 0, 1), Rgb::off(), Rgb::off()], frame.pixels());
+        assert!(read_hex(&mut reader, 3).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_hex_rgbw() {
+        let mut reader = Cursor::new("ff000080 00ff00\n");
+        let frame = read_hex(&mut reader, 2).unwrap().unwrap();
+        assert_eq!(&[Rgb::new_rgbw(255, 0, 0, 128), Rgb::new(0, 255, 0)], frame.pixels());
+    }
+
+    #[test]
+    fn test_hex_invalid() {
+        assert!(read_hex(&mut Cursor::new("ff000\n"), 1).is_err());
+        assert!(read_hex(&mut Cursor::new("ff00000\n"), 1).is_err());
+        assert!(read_hex(&mut Cursor::new("gg0000\n"), 1).is_err());
+        // More colors than LEDs
+        assert!(read_hex(&mut Cursor::new("ff0000 ff0000\n"), 1).is_err());
+    }
+}
@@ -0,0 +1,477 @@
+use std::fs;
+use std::io::{self, Stdout};
+use std::path::PathBuf;
+use std::time::Duration;
+use anyhow::{Context, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use serde::{Serialize, Deserialize};
+use tui::backend::{Backend, CrosstermBackend};
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color as TermColor, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Paragraph};
+use tui::{Frame, Terminal};
+use driver::{Color, Engine, Rgb};
+use crate::remote::{Daemon, StateChange};
+
+/// The palette has a key for every color, 1 to 9
+const PALETTE_SIZE: usize = 9;
+/// The width of the slider labels
+const LABEL_WIDTH: u16 = 12;
+/// The width of the slider values
+const VALUE_WIDTH: u16 = 6;
+
+/// Where the changes made in the TUI go
+pub enum Target<'a> {
+    /// The daemon, which stores them
+    Daemon(&'a Daemon),
+    /// The strip, written to directly
+    Strip(Engine),
+    /// Nowhere, they are only previewed
+    Preview,
+}
+
+impl Target<'_> {
+    /// The daemon fades the change in with its configured transition, like changes through Google
+    fn apply(&self, app: &App) -> Result<()> {
+        match self {
+            Self::Daemon(daemon) => {
+                let change = match app.on {
+                    true => StateChange {
+                        on: Some(true),
+                        brightness: Some(app.brightness),
+                        color: Some(Color::Rgb(app.color())),
+                        ..StateChange::default()
+                    },
+                    false => StateChange {
+                        on: Some(false),
+                        ..StateChange::default()
+                    },
+                };
+                daemon.change(&change)?;
+            },
+            Self::Strip(engine) => {
+                engine.set_brightness(app.brightness as f32 / 100.0)?;
+                engine.set_rgb(if app.on { app.color() } else { Rgb::off() })?;
+            },
+            Self::Preview => {},
+        }
+
+        Ok(())
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            Self::Daemon(_) => "through the daemon",
+            Self::Strip(_) => "writing to the strip",
+            Self::Preview => "preview only",
+        }
+    }
+}
+
+/// Colors saved in the TUI, kept in `~/.config/deskled/palette.toml`
+#[derive(Debug, Serialize, Deserialize)]
+struct Palette {
+    colors: Vec<Rgb>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            colors: ["#ffb46b", "#ff8800", "#ff0000", "#8800ff", "#0088ff"].iter()
+                .map(|x| x.parse().expect("Valid default palette"))
+                .collect(),
+        }
+    }
+}
+
+impl Palette {
+    fn path() -> Option<PathBuf> {
+        let config = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|x| PathBuf::from(x).join(".config")))?;
+        Some(config.join("deskled").join("palette.toml"))
+    }
+
+    /// The saved palette, or the default one if none was saved yet
+    fn load() -> Result<Self> {
+        let path = match Self::path() {
+            Some(x) if x.exists() => x,
+            _ => return Ok(Self::default()),
+        };
+
+        let contents = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_slice(&contents).with_context(|| format!("Invalid palette {}", path.display()))
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = Self::path().context("No home directory to save the palette in")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&path, toml::to_string(self)?).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Slider {
+    Hue,
+    Saturation,
+    Brightness,
+}
+
+impl Slider {
+    const ALL: [Slider; 3] = [Slider::Hue, Slider::Saturation, Slider::Brightness];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Hue => "Hue",
+            Self::Saturation => "Saturation",
+            Self::Brightness => "Brightness",
+        }
+    }
+}
+
+/// What a key press did
+enum Outcome {
+    Unchanged,
+    Changed,
+    Quit,
+}
+
+struct App {
+    /// In degrees
+    hue: u16,
+    /// In percent
+    saturation: u8,
+    /// In percent, at least 1. Turning the strip off is separate.
+    brightness: u8,
+    on: bool,
+    selected: Slider,
+    palette: Palette,
+    /// The palette color last picked, unless the color was adjusted since
+    picked: Option<usize>,
+    /// Shown at the bottom, e.g. when a change failed
+    message: Option<String>,
+}
+
+impl App {
+    /// The color at full brightness, the brightness is applied separately
+    fn color(&self) -> Rgb {
+        Rgb::from_hsv(self.hue as f32, self.saturation as f32 / 100.0, 1.0)
+    }
+
+    fn set_color(&mut self, rgb: Rgb) {
+        let (hue, saturation, _) = rgb.to_hsv();
+        self.hue = hue.round() as u16 % 360;
+        self.saturation = (saturation * 100.0).round() as u8;
+    }
+
+    /// How the strip looks
+    fn preview(&self) -> Rgb {
+        match self.on {
+            true => self.color().scale(self.brightness as f32 / 100.0),
+            false => Rgb::off(),
+        }
+    }
+
+    fn adjust(&mut self, steps: i32) {
+        match self.selected {
+            Slider::Hue => self.hue = (self.hue as i32 + steps).rem_euclid(360) as u16,
+            Slider::Saturation => self.saturation = (self.saturation as i32 + steps).clamp(0, 100) as u8,
+            Slider::Brightness => self.brightness = (self.brightness as i32 + steps).clamp(1, 100) as u8,
+        }
+        self.picked = None;
+    }
+
+    fn select(&mut self, offset: isize) {
+        let index = Slider::ALL.iter().position(|x| *x == self.selected).unwrap_or(0) as isize;
+        self.selected = Slider::ALL[(index + offset).rem_euclid(Slider::ALL.len() as isize) as usize];
+    }
+
+    fn handle(&mut self, key: KeyEvent) -> Outcome {
+        let step = if key.modifiers.contains(KeyModifiers::SHIFT) { 10 } else { 1 };
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Outcome::Quit,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Outcome::Quit,
+            KeyCode::Up | KeyCode::Char('k') => self.select(-1),
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => self.select(1),
+            KeyCode::Left | KeyCode::Char('h') => {
+                self.adjust(-step);
+                return Outcome::Changed;
+            },
+            KeyCode::Right | KeyCode::Char('l') => {
+                self.adjust(step);
+                return Outcome::Changed;
+            },
+            KeyCode::PageDown => {
+                self.adjust(-10);
+                return Outcome::Changed;
+            },
+            KeyCode::PageUp => {
+                self.adjust(10);
+                return Outcome::Changed;
+            },
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                self.on = !self.on;
+                return Outcome::Changed;
+            },
+            KeyCode::Char(c @ '1'..='9') => {
+                let index = c as usize - '1' as usize;
+                if let Some(rgb) = self.palette.colors.get(index).copied() {
+                    self.set_color(rgb);
+                    self.picked = Some(index);
+                    self.on = true;
+                    return Outcome::Changed;
+                }
+            },
+            KeyCode::Char('s') => self.save_color(),
+            KeyCode::Char('d') => self.delete_color(),
+            _ => {},
+        }
+
+        Outcome::Unchanged
+    }
+
+    fn save_color(&mut self) {
+        if self.palette.colors.len() >= PALETTE_SIZE {
+            self.message = Some("The palette is full, pick a color and delete it with d first".to_string());
+            return;
+        }
+
+        self.palette.colors.push(self.color());
+        self.picked = Some(self.palette.colors.len() - 1);
+        self.save_palette();
+    }
+
+    fn delete_color(&mut self) {
+        match self.picked.take() {
+            Some(index) => {
+                self.palette.colors.remove(index);
+                self.save_palette();
+            },
+            None => self.message = Some("Pick a color with 1-9 to delete it".to_string()),
+        }
+    }
+
+    fn save_palette(&mut self) {
+        if let Err(e) = self.palette.save() {
+            self.message = Some(format!("Failed to save the palette: {e:#}"));
+        }
+    }
+
+    fn slider_value(&self, slider: Slider) -> (f32, String) {
+        match slider {
+            Slider::Hue => (self.hue as f32 / 359.0, format!("{}°", self.hue)),
+            Slider::Saturation => (self.saturation as f32 / 100.0, format!("{}%", self.saturation)),
+            Slider::Brightness => (self.brightness as f32 / 100.0, format!("{}%", self.brightness)),
+        }
+    }
+
+    /// The color of a slider at a position in the range `0.0..=1.0`
+    fn slider_color(&self, slider: Slider, t: f32) -> Rgb {
+        let saturation = self.saturation as f32 / 100.0;
+        match slider {
+            Slider::Hue => Rgb::from_hsv(t * 359.0, 1.0, 1.0),
+            Slider::Saturation => Rgb::from_hsv(self.hue as f32, t, 1.0),
+            Slider::Brightness => Rgb::from_hsv(self.hue as f32, saturation, t),
+        }
+    }
+}
+
+/// Restores the terminal when dropped, also when bailing out with an error
+struct Screen(Terminal<CrosstermBackend<Stdout>>);
+
+impl Screen {
+    fn enter() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
+
+        let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+        terminal.hide_cursor()?;
+        Ok(Self(terminal))
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+        let _ = execute!(self.0.backend_mut(), LeaveAlternateScreen);
+        let _ = self.0.show_cursor();
+    }
+}
+
+/// Run the TUI until quit. Through the daemon, it starts from the state the daemon has stored,
+/// otherwise the strip is set to a warm white at `brightness` right away.
+pub fn run(target: Target, brightness: u8) -> Result<()> {
+    let mut app = App {
+        hue: 30,
+        saturation: 60,
+        brightness: brightness.max(1),
+        on: true,
+        selected: Slider::Hue,
+        palette: Palette::default(),
+        picked: None,
+        message: None,
+    };
+
+    match Palette::load() {
+        Ok(x) => app.palette = x,
+        Err(e) => app.message = Some(format!("{e:#}")),
+    }
+
+    match &target {
+        Target::Daemon(daemon) => {
+            let state = daemon.state()?;
+            app.set_color(match state.color {
+                Color::Rgb(rgb) => rgb,
+                Color::Kelvin(kelvin) => Rgb::from_kelvin(kelvin),
+            });
+            app.brightness = state.brightness.max(1);
+            app.on = state.on;
+        },
+        Target::Strip(_) | Target::Preview => target.apply(&app)?,
+    }
+
+    let mut screen = Screen::enter()?;
+    loop {
+        screen.0.draw(|f| draw(f, &app, &target))?;
+
+        if !event::poll(Duration::from_millis(250))? {
+            continue;
+        }
+
+        // Handle every key waiting at once, so holding down a key doesn't queue up changes
+        let mut changed = false;
+        loop {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    app.message = None;
+                    match app.handle(key) {
+                        Outcome::Unchanged => {},
+                        Outcome::Changed => changed = true,
+                        Outcome::Quit => return flush(&target),
+                    }
+                }
+            }
+
+            if !event::poll(Duration::ZERO)? {
+                break;
+            }
+        }
+
+        if changed {
+            if let Err(e) = target.apply(&app) {
+                app.message = Some(format!("Failed to apply the change: {e:#}"));
+            }
+        }
+    }
+}
+
+/// Make sure the last change was written before exiting
+fn flush(target: &Target) -> Result<()> {
+    if let Target::Strip(engine) = target {
+        engine.flush()?;
+    }
+
+    Ok(())
+}
+
+fn draw<B: Backend>(f: &mut Frame<B>, app: &App, target: &Target) {
+    let outer = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" deskled, {} ", target.describe()));
+    let area = outer.inner(f.size());
+    f.render_widget(outer, f.size());
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(5),
+            Constraint::Length(3),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ])
+        .split(area);
+
+    draw_preview(f, app, rows[0]);
+    draw_sliders(f, app, rows[1]);
+    draw_palette(f, app, rows[2]);
+
+    let help = "↑↓ select  ←→ adjust (shift: faster)  space on/off  1-9 pick  s save  d delete  q quit";
+    f.render_widget(Paragraph::new(Span::styled(help, Style::default().fg(TermColor::DarkGray))), rows[3]);
+
+    if let Some(message) = &app.message {
+        f.render_widget(Paragraph::new(Span::styled(message.as_str(), Style::default().fg(TermColor::Red))), rows[4]);
+    }
+}
+
+fn draw_preview<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let title = match app.on {
+        true => format!(" On, {} ", app.color()),
+        false => " Off ".to_string(),
+    };
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let inner = block.inner(area);
+    let line = Span::styled(" ".repeat(inner.width as usize), Style::default().bg(term_color(app.preview())));
+
+    f.render_widget(Paragraph::new(line).block(block), area);
+}
+
+fn draw_sliders<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let block = Block::default().borders(Borders::ALL);
+    let width = block.inner(area).width.saturating_sub(LABEL_WIDTH + VALUE_WIDTH).max(2) as usize;
+
+    let lines = Slider::ALL.iter().map(|slider| {
+        let selected = *slider == app.selected;
+        let label_style = match selected {
+            true => Style::default().add_modifier(Modifier::BOLD),
+            false => Style::default(),
+        };
+        let label = format!("{}{}", if selected { "▶ " } else { "  " }, slider.label());
+        let (value, text) = app.slider_value(*slider);
+        let knob = (value * (width - 1) as f32).round() as usize;
+
+        let mut spans = vec![Span::styled(format!("{label:<width$}", width = LABEL_WIDTH as usize), label_style)];
+        spans.extend((0..width).map(|i| {
+            let color = term_color(app.slider_color(*slider, i as f32 / (width - 1) as f32));
+            match i == knob {
+                true => Span::styled("┃", Style::default().bg(color).fg(TermColor::White).add_modifier(Modifier::BOLD)),
+                false => Span::styled(" ", Style::default().bg(color)),
+            }
+        }));
+        spans.push(Span::styled(format!("{text:>width$}", width = VALUE_WIDTH as usize), label_style));
+
+        Spans::from(spans)
+    }).collect::<Vec<_>>();
+
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn draw_palette<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let mut spans = Vec::new();
+    for (i, rgb) in app.palette.colors.iter().enumerate() {
+        let style = match app.picked == Some(i) {
+            true => Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            false => Style::default(),
+        };
+        spans.push(Span::styled(format!(" {} ", i + 1), style));
+        spans.push(Span::styled("    ", Style::default().bg(term_color(*rgb))));
+    }
+    if spans.is_empty() {
+        spans.push(Span::styled(" Press s to save the current color", Style::default().fg(TermColor::DarkGray)));
+    }
+
+    let block = Block::default().borders(Borders::ALL).title(" Palette ");
+    f.render_widget(Paragraph::new(Spans::from(spans)).block(block), area);
+}
+
+fn term_color(rgb: Rgb) -> TermColor {
+    TermColor::Rgb(rgb.r, rgb.g, rgb.b)
+}
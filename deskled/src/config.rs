@@ -1,6 +1,7 @@
 use serde::{Serialize, Deserialize};
 use std::io;
 use std::path::Path;
+use driver::{Chipset, Error, ColorOrder, Correction, DEFAULT_STRIP, EffectKind, EffectParams, KelvinCalibration, Layout, NetworkProtocol, PixelFormat, PowerBudget, Transition, WhiteExtraction, Zone};
 use tokio::fs;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tracing::debug;
@@ -11,13 +12,167 @@ pub struct Config {
     pub oauth2: Oauth2,
     pub login: Login,
     pub led: Led,
+    #[serde(default)]
+    pub control: Control,
+    /// Separate strips driven from the same daemon, e.g. on SPI0 and SPI1.
+    /// The settings in `[led]` apply to every strip, except for those configured here.
+    /// Only the strip configured in `[led]` is used if none are configured.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub strips: Vec<Strip>,
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Led {
+    // Plain values have to come before tables, or the default config can't be serialized
+    /// The number of LEDs on the strip, only required if no `[[strips]]` are configured
+    pub length: Option<u16>,
+    /// Where frames are written to
+    #[serde(default)]
+    pub backend: Backend,
+    /// The SPI device, either a path like `/dev/spidev0.0` or bus and chip select like `0.0`.
+    /// The first SPI device found is used if not set.
+    pub device: Option<String>,
+    /// The SPI clock speed in Hz
+    pub spi_speed_hz: Option<u32>,
+    /// The LED controller used by the strip
+    #[serde(default)]
+    pub chipset: Chipset,
+    #[serde(default)]
+    pub color_order: ColorOrder,
+    /// The frame rate effects are rendered at, defaults to 60
+    pub fps: Option<u32>,
+    /// Dither colors too dim for 8 bits by refreshing the strip continuously
+    #[serde(default)]
+    pub dither: bool,
+    /// Rewrite the strip at this interval while nothing changes, to recover from glitches
+    pub refresh_ms: Option<u64>,
+    /// Named sections of the strip which can be colored independently.
+    /// The whole strip is a single zone if none are configured.
+    #[serde(default)]
+    pub zones: Vec<Zone>,
+    /// Set for RGBW strips
+    #[serde(default)]
+    pub white: Option<WhiteExtraction>,
+    /// Drive the strip through a controller on the network, like WLED, instead of SPI
+    pub remote: Option<Remote>,
+    /// Where the pixels of frames are on the strip
+    #[serde(default)]
+    pub layout: Layout,
+    /// How color temperatures look on the strip
+    #[serde(default)]
+    pub kelvin: KelvinCalibration,
+    #[serde(default)]
+    pub correction: Correction,
+    #[serde(default)]
+    pub power: PowerBudget,
+    /// How changes in color and brightness are faded in.
+    /// Changes are applied immediately if not set.
+    pub transition: Option<Transition>,
+    /// The effect to start when the daemon starts
+    pub effect: Option<Effect>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Physical strips, connected over SPI or through a controller on the network
+    #[default]
+    Spi,
+    /// Simulate the strip in the terminal the daemon runs in
+    Terminal,
+}
+
+/// A controller on the network frames are sent to over UDP.
+/// The controller takes care of the chipset and color order, so those settings are ignored.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Remote {
+    pub protocol: NetworkProtocol,
+    /// Host name or IP address of the controller, with an optional port
+    pub address: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Effect {
+    pub kind: EffectKind,
+    #[serde(flatten)]
+    pub params: EffectParams,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Strip {
+    /// The name by which the strip is addressed individually
+    pub name: String,
     pub length: u16,
+    /// The SPI device, either a path like `/dev/spidev1.0` or bus and chip select like `1.0`
+    pub device: Option<String>,
+    /// The SPI clock speed in Hz
+    pub spi_speed_hz: Option<u32>,
+    /// The LED controller used by the strip
+    #[serde(default)]
+    pub chipset: Chipset,
+    #[serde(default)]
+    pub color_order: ColorOrder,
+    #[serde(default)]
+    pub zones: Vec<Zone>,
+    /// Set for RGBW strips
+    #[serde(default)]
+    pub white: Option<WhiteExtraction>,
+    /// Drive the strip through a controller on the network, like WLED, instead of SPI
+    pub remote: Option<Remote>,
+    /// Where the pixels of frames are on the strip
+    #[serde(default)]
+    pub layout: Layout,
+    /// How color temperatures look on the strip
+    #[serde(default)]
+    pub kelvin: KelvinCalibration,
+}
+
+impl Strip {
+    pub fn pixel_format(&self) -> PixelFormat {
+        PixelFormat {
+            chipset: self.chipset,
+            color_order: self.color_order,
+            white: self.white,
+        }
+    }
 }
 
+impl Config {
+    /// The configured strips, or the strip configured in `[led]` if there are none
+    pub fn strips(&self) -> driver::Result<Vec<Strip>> {
+        if !self.strips.is_empty() {
+            return Ok(self.strips.clone());
+        }
+
+        let length = self.led.length
+            .ok_or_else(|| Error::Strip("The length of the strip is required in [led] without any [[strips]]".to_string()))?;
+        Ok(vec![Strip {
+            name: DEFAULT_STRIP.to_string(),
+            length,
+            device: self.led.device.clone(),
+            spi_speed_hz: self.led.spi_speed_hz,
+            chipset: self.led.chipset,
+            color_order: self.led.color_order,
+            zones: self.led.zones.clone(),
+            white: self.led.white,
+            remote: self.led.remote.clone(),
+            layout: self.led.layout.clone(),
+            kelvin: self.led.kelvin.clone(),
+        }])
+    }
+}
+
+/// The local API through which the CLI controls the strips while the daemon runs
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Control {
+    /// The address the API listens on, defaults to [DEFAULT_CONTROL_ADDRESS].
+    /// It needs no authorization, so it must not be reachable from other machines.
+    pub address: Option<String>,
+}
+
+/// Only reachable from the Pi itself
+pub const DEFAULT_CONTROL_ADDRESS: &str = "127.0.0.1:8081";
+
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Mysql {
     pub host: String,
@@ -1,7 +1,8 @@
 use std::process::exit;
+use std::time::Duration;
 use tracing::error;
-use driver::{Driver, Spidev};
-use crate::config::Config;
+use driver::{DEFAULT_FPS, Driver, Engine, NetworkOutput, Spidev, Strips, TerminalOutput};
+use crate::config::{Backend, Config, DEFAULT_CONTROL_ADDRESS, Led, Remote, Strip};
 
 mod config;
 
@@ -21,32 +22,59 @@ async fn do_main() {
         }
     };
 
-    let spidev = match Spidev::new() {
+    let strips = match config.strips() {
         Ok(x) => x,
         Err(e) => {
-            error!("Failed to open Spidev: {e}");
+            error!("Invalid strip configuration: {e}");
             exit(1);
         }
     };
-    let driver = match Driver::new(&spidev, config.led.length) {
+    if config.led.backend == Backend::Terminal && strips.len() > 1 {
+        error!("The terminal backend can only simulate a single strip");
+        exit(1);
+    }
+
+    let engines = strips.iter()
+        .map(|strip| (strip.name.clone(), spawn_engine(&config.led, strip)))
+        .collect();
+    let strips = match Strips::new(engines) {
         Ok(x) => x,
         Err(e) => {
-            error!("Failed to create driver: {e}");
+            error!("Invalid strip configuration: {e}");
             exit(1);
         }
     };
 
+    if let Some(transition) = config.led.transition {
+        if let Err(e) = strips.set_transition(transition) {
+            error!("Failed to set transition: {e}");
+            exit(1);
+        }
+    }
+    if let Some(refresh_ms) = config.led.refresh_ms {
+        if let Err(e) = strips.set_refresh_interval(Some(Duration::from_millis(refresh_ms))) {
+            error!("Failed to set refresh interval: {e}");
+            exit(1);
+        }
+    }
+    if let Some(effect) = &config.led.effect {
+        if let Err(e) = strips.start_effect(effect.kind, &effect.params) {
+            error!("Failed to start effect: {e}");
+            exit(1);
+        }
+    }
+
     match ghome::start(ghome::Config {
         mysql_host: config.mysql.host,
         mysql_username: config.mysql.username,
         mysql_password: config.mysql.password,
         mysql_database: config.mysql.database,
-        led_length: config.led.length,
         oauth2_client_id: config.oauth2.client_id,
         oauth2_client_secret: config.oauth2.client_secret,
         login_username: config.login.username,
-        login_password: config.login.password
-    }, driver).await {
+        login_password: config.login.password,
+        control_address: config.control.address.unwrap_or_else(|| DEFAULT_CONTROL_ADDRESS.to_string()),
+    }, strips).await {
         Ok(_) => {},
         Err(e) => {
             error!("Failed to start webserver: {e}");
@@ -55,6 +83,67 @@ async fn do_main() {
     }
 }
 
+/// Create the driver for a strip, with the settings shared by every strip
+fn spawn_engine(led: &Led, strip: &Strip) -> Engine {
+    let mut driver = match led.backend {
+        Backend::Spi => match &strip.remote {
+            Some(remote) => open_remote(strip, remote),
+            None => open_spi(strip),
+        },
+        Backend::Terminal => Driver::with_output(Box::new(TerminalOutput::stdout()), strip.length),
+    };
+    driver.set_correction(led.correction);
+    driver.set_power_budget(led.power);
+    driver.set_dithering(led.dither);
+    if let Err(e) = strip.kelvin.validate(strip.white.is_some()) {
+        error!("Invalid kelvin calibration for strip '{}': {e}", strip.name);
+        exit(1);
+    }
+    driver.set_kelvin_calibration(strip.kelvin.clone());
+    if let Err(e) = driver.set_layout(strip.layout.clone()) {
+        error!("Invalid layout for strip '{}': {e}", strip.name);
+        exit(1);
+    }
+    if let Err(e) = driver.set_zones(strip.zones.clone()) {
+        error!("Invalid zone configuration for strip '{}': {e}", strip.name);
+        exit(1);
+    }
+
+    Engine::spawn(driver, led.fps.unwrap_or(DEFAULT_FPS))
+}
+
+fn open_spi(strip: &Strip) -> Driver {
+    let spidev = match &strip.device {
+        Some(device) => Spidev::find(device),
+        None => Spidev::new(),
+    };
+    let spidev = match spidev {
+        Ok(x) => x.with_speed(strip.spi_speed_hz.unwrap_or(Spidev::DEFAULT_SPEED_HZ)),
+        Err(e) => {
+            error!("Failed to open Spidev for strip '{}': {e}", strip.name);
+            exit(1);
+        }
+    };
+
+    match Driver::new(&spidev, strip.length, strip.pixel_format()) {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to create driver for strip '{}': {e}", strip.name);
+            exit(1);
+        }
+    }
+}
+
+fn open_remote(strip: &Strip, remote: &Remote) -> Driver {
+    match NetworkOutput::new(&remote.address, remote.protocol, strip.white) {
+        Ok(x) => Driver::with_output(Box::new(x), strip.length),
+        Err(e) => {
+            error!("Failed to connect to {} for strip '{}': {e}", remote.address, strip.name);
+            exit(1);
+        }
+    }
+}
+
 pub fn setup_tracing() {
     let sub = tracing_subscriber::fmt()
         .compact()
@@ -0,0 +1,39 @@
+use std::hint::black_box;
+use std::io;
+use criterion::{criterion_group, criterion_main, Criterion};
+use driver::{Driver, FileOutput, Frame, PixelFormat, Rgb};
+
+/// At 60 FPS a frame has to be ready within 16.7 ms.
+/// Sending 300 RGB LEDs to the strip already takes 9 ms of that,
+/// so encoding should stay well below the remaining 7 ms, even on a Pi Zero.
+const LENGTH: u16 = 300;
+
+fn driver() -> Driver {
+    let output = FileOutput::new(io::sink(), PixelFormat::default()).unwrap();
+    Driver::with_output(Box::new(output), LENGTH)
+}
+
+fn every_led_changes(c: &mut Criterion) {
+    let mut driver = driver();
+    let mut frame = Frame::new(LENGTH);
+    let mut offset = 0u8;
+
+    c.bench_function("300 LEDs, every LED changes", |b| b.iter(|| {
+        offset = offset.wrapping_add(1);
+        for (i, pixel) in frame.pixels_mut().iter_mut().enumerate() {
+            let value = offset.wrapping_add(i as u8);
+            *pixel = Rgb::new(value, value.wrapping_mul(3), 255 - value);
+        }
+        driver.write_frame(black_box(&frame)).unwrap();
+    }));
+}
+
+fn nothing_changes(c: &mut Criterion) {
+    let mut driver = driver();
+    c.bench_function("300 LEDs, nothing changes", |b| b.iter(|| {
+        driver.set_rgb(black_box(Rgb::new(255, 136, 0))).unwrap();
+    }));
+}
+
+criterion_group!(benches, every_led_changes, nothing_changes);
+criterion_main!(benches);
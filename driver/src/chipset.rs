@@ -0,0 +1,209 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use crate::encoding::{PixelFormat, Ws28xxEncoder, Ws28xxTiming};
+use crate::error::{Error, Result};
+use crate::{Rgb, Rgb16};
+
+/// The LED controller used by a strip, which determines how frames are sent to it.
+/// WS28xx strips only use the SPI data line, the other chipsets also use the SPI clock line
+/// and are therefore not affected by timing jitter.
+/// Only WS28xx strips can have a white channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Chipset {
+    /// WS2811, WS2812(B), WS2815, SK6812 and compatibles
+    #[default]
+    Ws28xx,
+    /// APA102 and SK9822. Their 5 bit per-LED brightness is used to show
+    /// dim colors with more precision than 8 bits would allow.
+    #[serde(alias = "sk9822")]
+    Apa102,
+    Ws2801,
+    /// 7 bits per channel
+    Lpd8806,
+}
+
+impl FromStr for Chipset {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "ws28xx" | "ws2811" | "ws2812" | "ws2812b" | "sk6812" => Self::Ws28xx,
+            "apa102" | "sk9822" => Self::Apa102,
+            "ws2801" => Self::Ws2801,
+            "lpd8806" => Self::Lpd8806,
+            _ => return Err(Error::Chipset(s.to_string()))
+        })
+    }
+}
+
+impl fmt::Display for Chipset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Ws28xx => "ws28xx",
+            Self::Apa102 => "apa102",
+            Self::Ws2801 => "ws2801",
+            Self::Lpd8806 => "lpd8806",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Turns frames into the bytes sent over SPI for a [Chipset].
+/// Frames are encoded into a buffer which is reused for the next frame,
+/// so nothing is allocated unless the length of the frames changes.
+#[derive(Debug, Clone)]
+pub(crate) struct Encoder {
+    format: PixelFormat,
+    protocol: Protocol,
+    buffer: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+enum Protocol {
+    Ws28xx(Ws28xxEncoder),
+    Apa102,
+    Ws2801,
+    Lpd8806,
+}
+
+impl Encoder {
+    /// Fails if the format has a white channel the chipset doesn't have
+    pub(crate) fn new(format: PixelFormat, speed_hz: u32) -> Result<Self> {
+        if format.white.is_some() && format.chipset != Chipset::Ws28xx {
+            return Err(Error::WhiteChannel(format.chipset));
+        }
+
+        let protocol = match format.chipset {
+            Chipset::Ws28xx => Protocol::Ws28xx(Ws28xxEncoder::new(Ws28xxTiming::new(speed_hz)?)),
+            Chipset::Apa102 => Protocol::Apa102,
+            Chipset::Ws2801 => Protocol::Ws2801,
+            Chipset::Lpd8806 => Protocol::Lpd8806,
+        };
+
+        Ok(Self {
+            format,
+            protocol,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Whether the chipset can show more than 8 bits per channel,
+    /// so frames should be encoded with [Encoder::encode_wide]
+    pub(crate) fn is_wide(&self) -> bool {
+        matches!(self.protocol, Protocol::Apa102)
+    }
+
+    pub(crate) fn encode(&mut self, pixels: &[Rgb]) -> &[u8] {
+        self.encode_rgb(pixels.iter().copied())
+    }
+
+    pub(crate) fn encode_wide(&mut self, pixels: &[Rgb16]) -> &[u8] {
+        match self.protocol {
+            Protocol::Apa102 => {
+                encode_apa102(pixels.iter().copied(), self.format, &mut self.buffer);
+                &self.buffer
+            },
+            _ => self.encode_rgb(pixels.iter().map(Rgb16::to_rgb)),
+        }
+    }
+
+    fn encode_rgb<I: ExactSizeIterator<Item = Rgb>>(&mut self, pixels: I) -> &[u8] {
+        let (format, out) = (self.format, &mut self.buffer);
+        match &mut self.protocol {
+            Protocol::Ws28xx(encoder) => encoder.encode(pixels, format, out),
+            Protocol::Apa102 => encode_apa102(pixels.map(Rgb16::from), format, out),
+            Protocol::Ws2801 => {
+                out.clear();
+                out.extend(pixels.flat_map(|x| format.color_order.apply(x)));
+            },
+            Protocol::Lpd8806 => encode_lpd8806(pixels, format, out),
+        }
+        &self.buffer
+    }
+}
+
+/// Encode for APA102 and SK9822 LEDs. Every LED is sent at the lowest global brightness
+/// which still fits its brightest channel, leaving more of the 8 bits of the channels for precision.
+fn encode_apa102<I: ExactSizeIterator<Item = Rgb16>>(pixels: I, format: PixelFormat, out: &mut Vec<u8>) {
+    let length = pixels.len();
+
+    // Start frame
+    out.clear();
+    out.extend([0u8; 4]);
+
+    for pixel in pixels {
+        let brightest = pixel.r.max(pixel.g).max(pixel.b) as u32;
+        let global = (brightest * 31).div_ceil(65535).max(1);
+        let channel = |x: u16| ((x as u32 * 31 * 255 + global * 65535 / 2) / (global * 65535)).min(255) as u8;
+
+        out.push(0b1110_0000 | global as u8);
+        out.extend(format.color_order.reorder([channel(pixel.r), channel(pixel.g), channel(pixel.b)]));
+    }
+
+    // The SK9822 needs a reset frame to latch, which the APA102 ignores.
+    // Both then need a clock edge for every 2 LEDs to push the data to the end of the strip.
+    out.resize(out.len() + 4 + length.div_ceil(16), 0);
+}
+
+/// Encode for LPD8806 LEDs, which take 7 bits per channel with the highest bit set,
+/// followed by zeroes to latch the frame
+fn encode_lpd8806<I: ExactSizeIterator<Item = Rgb>>(pixels: I, format: PixelFormat, out: &mut Vec<u8>) {
+    let length = pixels.len();
+    out.clear();
+    out.extend(pixels
+        .flat_map(|x| format.color_order.apply(x))
+        .map(|x| 0x80 | x >> 1));
+    out.resize(out.len() + length.div_ceil(32), 0);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::chipset::Encoder;
+    use crate::{Chipset, ColorOrder, Error, PixelFormat, Rgb, Rgb16, Spidev, WhiteExtraction};
+
+    const BGR: PixelFormat = PixelFormat { chipset: Chipset::Apa102, color_order: ColorOrder::Bgr, white: None };
+
+    #[test]
+    fn test_apa102() {
+        let mut encoder = Encoder::new(BGR, Spidev::DEFAULT_SPEED_HZ).unwrap();
+        let bytes = encoder.encode(&[Rgb::new(255, 128, 0), Rgb::off()]);
+        assert_eq!([0, 0, 0, 0], bytes[0..4]);
+        assert_eq!([0xFF, 0, 128, 255], bytes[4..8]);
+        assert_eq!([0xE1, 0, 0, 0], bytes[8..12]);
+        assert_eq!(12 + 4 + 1, bytes.len());
+    }
+
+    #[test]
+    fn test_apa102_global_brightness() {
+        // Far too dim for 8 bits, but with the global brightness at 1/31 the hue survives
+        let mut encoder = Encoder::new(BGR, Spidev::DEFAULT_SPEED_HZ).unwrap();
+        let bytes = encoder.encode_wide(&[Rgb16::new_rgbw(600, 300, 0, 0)]);
+        assert_eq!([0xE1, 0, 36, 72], bytes[4..8]);
+    }
+
+    #[test]
+    fn test_lpd8806() {
+        let format = PixelFormat { chipset: Chipset::Lpd8806, color_order: ColorOrder::Grb, white: None };
+        let mut encoder = Encoder::new(format, Spidev::DEFAULT_SPEED_HZ).unwrap();
+        let bytes = encoder.encode(&[Rgb::new(255, 2, 0)]);
+        assert_eq!([0x81, 0xFF, 0x80, 0], bytes[..]);
+    }
+
+    #[test]
+    fn test_white_channel() {
+        let rgbw = |chipset| PixelFormat { chipset, color_order: ColorOrder::Grb, white: Some(WhiteExtraction::Min) };
+        assert!(Encoder::new(rgbw(Chipset::Ws28xx), Spidev::DEFAULT_SPEED_HZ).is_ok());
+        for chipset in [Chipset::Apa102, Chipset::Ws2801, Chipset::Lpd8806] {
+            assert!(matches!(Encoder::new(rgbw(chipset), Spidev::DEFAULT_SPEED_HZ), Err(Error::WhiteChannel(_))));
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Chipset::Apa102, "SK9822".parse().unwrap());
+        assert_eq!(Chipset::Ws28xx, "ws2812b".parse().unwrap());
+        assert!("ws2813x".parse::<Chipset>().is_err());
+    }
+}
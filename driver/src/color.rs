@@ -0,0 +1,341 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use crate::css;
+use crate::error::Error;
+
+/// Serialized as a hex string, e.g. `#ff8800`, or `#ff880040` if the white channel is used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    /// The dedicated white channel of RGBW strips.
+    /// Ignored by strips without a white channel.
+    pub w: u8,
+}
+
+impl Rgb {
+    pub fn new(red: u8, green: u8, blue: u8) -> Self {
+        Self::new_rgbw(red, green, blue, 0)
+    }
+
+    pub fn new_rgbw(red: u8, green: u8, blue: u8, white: u8) -> Self {
+        Self {
+            r: red,
+            g: green,
+            b: blue,
+            w: white,
+        }
+    }
+
+    pub fn off() -> Self {
+        Self::new(0, 0, 0)
+    }
+
+    /// Create a color from a hue in degrees, and a saturation and value in the range `0.0..=1.0`
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let hue = hue.rem_euclid(360.0) / 60.0;
+        let saturation = saturation.clamp(0.0, 1.0);
+        let value = value.clamp(0.0, 1.0);
+
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let (r, g, b) = match hue as u8 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+
+        let m = value - chroma;
+        let channel = |x: f32| ((x + m) * 255.0).round() as u8;
+        Self::new(channel(r), channel(g), channel(b))
+    }
+
+    /// The hue in degrees, and the saturation and value in the range `0.0..=1.0`.
+    /// The inverse of [Rgb::from_hsv], ignoring the white channel.
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let r = self.r as f32 / 255.0;
+        let g = self.g as f32 / 255.0;
+        let b = self.b as f32 / 255.0;
+
+        let max = r.max(g).max(b);
+        let chroma = max - r.min(g).min(b);
+        let hue = if chroma == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / chroma).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / chroma + 2.0)
+        } else {
+            60.0 * ((r - g) / chroma + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+
+        (hue, saturation, max)
+    }
+
+    /// Multiply every channel by `factor`, in the range `0.0..=1.0`
+    pub fn scale(&self, factor: f32) -> Self {
+        let factor = factor.clamp(0.0, 1.0);
+        let channel = |x: u8| (x as f32 * factor).round() as u8;
+        Self::new_rgbw(channel(self.r), channel(self.g), channel(self.b), channel(self.w))
+    }
+
+    /// Linearly interpolate towards `other`. `0.0` is this color, `1.0` is `other`.
+    pub fn lerp(&self, other: Rgb, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let channel = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * t).round() as u8;
+        Self::new_rgbw(channel(self.r, other.r), channel(self.g, other.g), channel(self.b, other.b), channel(self.w, other.w))
+    }
+
+    /// Approximate the color of a black body at the given temperature in Kelvin.
+    /// The brightest channel is always at full brightness.
+    /// Valid between roughly 1000K and 40000K.
+    pub fn from_kelvin(kelvin: u16) -> Self {
+        // Curve fit by Tanner Helland, based on Mitchell Charity's black body data
+        let temperature = kelvin as f64 / 100.0;
+
+        let r = if temperature <= 66.0 {
+            255.0
+        } else {
+            329.698727446 * (temperature - 60.0).powf(-0.1332047592)
+        };
+
+        let g = if temperature <= 66.0 {
+            99.4708025861 * temperature.ln() - 161.1195681661
+        } else {
+            288.1221695283 * (temperature - 60.0).powf(-0.0755148492)
+        };
+
+        let b = if temperature >= 66.0 {
+            255.0
+        } else if temperature <= 19.0 {
+            0.0
+        } else {
+            138.5177312231 * (temperature - 10.0).ln() - 305.0447927307
+        };
+
+        let clamp = |x: f64| x.clamp(0.0, 255.0).round() as u8;
+        Self::new(clamp(r), clamp(g), clamp(b))
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = Error;
+
+    /// Parses a hex color like `#ff8800`, or `#ff880040` to include the white channel,
+    /// a CSS color name like `orange`, or a hue in degrees with saturation and value in percent
+    /// like `hsv(30, 100%, 100%)`. The leading `#` of hex colors is optional.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(rgb) = css::named(s) {
+            return Ok(rgb);
+        }
+
+        let lower = s.to_lowercase();
+        if let Some(hsv) = lower.strip_prefix("hsv(").and_then(|x| x.strip_suffix(')')) {
+            let values = hsv.split(',')
+                .map(|x| x.trim().trim_end_matches('%').parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| Error::Color(s.to_string()))?;
+            return match values[..] {
+                [hue, saturation, value] => Ok(Self::from_hsv(hue, saturation / 100.0, value / 100.0)),
+                _ => Err(Error::Color(s.to_string())),
+            };
+        }
+
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+            return Err(Error::Color(s.to_string()));
+        }
+
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| Error::Color(s.to_string()));
+        let white = if hex.len() == 8 { channel(6)? } else { 0 };
+        Ok(Self::new_rgbw(channel(0)?, channel(2)?, channel(4)?, white))
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)?;
+        if self.w > 0 {
+            write!(f, "{:02x}", self.w)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for Rgb {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Rgb> for String {
+    fn from(rgb: Rgb) -> Self {
+        rgb.to_string()
+    }
+}
+
+/// A color as it was asked for. Color temperatures are only converted to a color
+/// when shown on a strip, so every strip can use its own [crate::KelvinCalibration].
+/// Serialized as a string, e.g. `#ff8800` or `2700K`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Color {
+    Rgb(Rgb),
+    /// A color temperature in Kelvin, e.g. `2700` for warm white
+    Kelvin(u16),
+}
+
+impl From<Rgb> for Color {
+    fn from(rgb: Rgb) -> Self {
+        Self::Rgb(rgb)
+    }
+}
+
+impl FromStr for Color {
+    type Err = Error;
+
+    /// Parses a color temperature like `2700K`, or any color [Rgb] can be parsed from
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Not every color ending in a `k` is a temperature, like `black`
+        match s.strip_suffix(['k', 'K']).and_then(|x| x.parse().ok()) {
+            Some(kelvin) => Ok(Self::Kelvin(kelvin)),
+            None => s.parse().map(Self::Rgb),
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rgb(rgb) => write!(f, "{rgb}"),
+            Self::Kelvin(kelvin) => write!(f, "{kelvin}K"),
+        }
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> Self {
+        color.to_string()
+    }
+}
+
+/// A color with 16 bits per channel, used between correction and writing to the strip
+/// so dim colors keep their hue. `65535` is full brightness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rgb16 {
+    pub r: u16,
+    pub g: u16,
+    pub b: u16,
+    pub w: u16,
+}
+
+impl Rgb16 {
+    pub fn new_rgbw(red: u16, green: u16, blue: u16, white: u16) -> Self {
+        Self {
+            r: red,
+            g: green,
+            b: blue,
+            w: white,
+        }
+    }
+
+    pub fn channels(&self) -> [u16; 4] {
+        [self.r, self.g, self.b, self.w]
+    }
+
+    /// Round every channel to the nearest 8 bit value
+    pub fn to_rgb(&self) -> Rgb {
+        let channel = |x: u16| ((x as u32 * 255 + 32767) / 65535) as u8;
+        Rgb::new_rgbw(channel(self.r), channel(self.g), channel(self.b), channel(self.w))
+    }
+}
+
+impl From<Rgb> for Rgb16 {
+    fn from(rgb: Rgb) -> Self {
+        let channel = |x: u8| x as u16 * 257;
+        Self::new_rgbw(channel(rgb.r), channel(rgb.g), channel(rgb.b), channel(rgb.w))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Color, Rgb};
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(Rgb::new(255, 136, 0), "#ff8800".parse().unwrap());
+        assert_eq!(Rgb::new_rgbw(255, 136, 0, 64), "FF880040".parse().unwrap());
+        assert!("#ff88".parse::<Rgb>().is_err());
+        assert_eq!("#ff8800", Rgb::new(255, 136, 0).to_string());
+    }
+
+    #[test]
+    fn test_names_and_hsv() {
+        assert_eq!(Rgb::new(255, 165, 0), "orange".parse().unwrap());
+        assert_eq!(Rgb::new(102, 51, 153), "RebeccaPurple".parse().unwrap());
+        assert_eq!(Rgb::new(0, 0, 128), "hsv(240, 100%, 50%)".parse().unwrap());
+        assert_eq!(Rgb::new(255, 0, 0), "HSV(0,100,100)".parse().unwrap());
+        assert!("hsv(0, 100%)".parse::<Rgb>().is_err());
+        assert!("notacolor".parse::<Rgb>().is_err());
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(Color::Kelvin(2700), "2700K".parse().unwrap());
+        assert_eq!(Color::Rgb(Rgb::new(255, 136, 0)), "#ff8800".parse().unwrap());
+        assert!("warmK".parse::<Color>().is_err());
+        assert_eq!(Color::Rgb(Rgb::off()), "black".parse().unwrap());
+        assert_eq!("2700K", Color::Kelvin(2700).to_string());
+    }
+
+    #[test]
+    fn test_from_hsv() {
+        assert_eq!(Rgb::new(255, 0, 0), Rgb::from_hsv(0.0, 1.0, 1.0));
+        assert_eq!(Rgb::new(0, 255, 0), Rgb::from_hsv(120.0, 1.0, 1.0));
+        assert_eq!(Rgb::new(0, 0, 128), Rgb::from_hsv(240.0, 1.0, 0.5));
+        assert_eq!(Rgb::new(255, 255, 255), Rgb::from_hsv(42.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_to_hsv() {
+        assert_eq!((0.0, 1.0, 1.0), Rgb::new(255, 0, 0).to_hsv());
+        assert_eq!((240.0, 1.0, 1.0), Rgb::new(0, 0, 255).to_hsv());
+        assert_eq!((0.0, 0.0, 0.0), Rgb::off().to_hsv());
+
+        for rgb in [Rgb::new(255, 136, 0), Rgb::new(12, 200, 99), Rgb::new(80, 40, 160)] {
+            let (hue, saturation, value) = rgb.to_hsv();
+            assert_eq!(rgb, Rgb::from_hsv(hue, saturation, value));
+        }
+    }
+
+    #[test]
+    fn test_from_kelvin() {
+        assert_eq!(Rgb::new(255, 255, 255), Rgb::from_kelvin(6600));
+
+        let warm = Rgb::from_kelvin(2700);
+        assert_eq!(255, warm.r);
+        assert!(warm.g < 255 && warm.b < warm.g);
+
+        let cold = Rgb::from_kelvin(10000);
+        assert_eq!(255, cold.b);
+        assert!(cold.r < 255);
+    }
+}
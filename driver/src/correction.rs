@@ -0,0 +1,184 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use crate::error::Error;
+use crate::{Rgb, Rgb16};
+
+/// Corrections applied to every color before it is written to the strip
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Correction {
+    /// The gamma exponent applied to each channel.
+    /// `1.0` writes colors as-is, `2.2` to `2.8` is typical for WS281x LEDs.
+    pub gamma: f32,
+    /// How the driver's brightness level maps to LED output
+    pub brightness_curve: BrightnessCurve,
+    /// Scale factors for each channel, to correct the tint of white.
+    /// Kept last, as TOML requires tables to come after plain values.
+    pub white_balance: WhiteBalance,
+}
+
+impl Default for Correction {
+    fn default() -> Self {
+        Self {
+            gamma: 1.0,
+            brightness_curve: BrightnessCurve::default(),
+            white_balance: WhiteBalance::default(),
+        }
+    }
+}
+
+/// Scale factors for each channel, in the range `0.0..=1.0`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WhiteBalance {
+    pub red: f32,
+    pub green: f32,
+    pub blue: f32,
+}
+
+impl Default for WhiteBalance {
+    fn default() -> Self {
+        Self {
+            red: 1.0,
+            green: 1.0,
+            blue: 1.0,
+        }
+    }
+}
+
+impl FromStr for WhiteBalance {
+    type Err = Error;
+
+    /// Parses three comma separated factors, e.g. `1.0,0.85,0.7`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let factors = s.split(',')
+            .map(|x| x.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| Error::WhiteBalance(s.to_string()))?;
+
+        match factors.as_slice() {
+            &[red, green, blue] => Ok(Self { red, green, blue }),
+            _ => Err(Error::WhiteBalance(s.to_string()))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrightnessCurve {
+    /// Output is directly proportional to the brightness level
+    Linear,
+    /// The brightness level is treated as CIE L* lightness,
+    /// so equal steps in brightness look like equal steps to the eye
+    #[default]
+    Cie,
+}
+
+impl BrightnessCurve {
+    /// Map a brightness level in `0.0..=1.0` to a relative luminance in `0.0..=1.0`
+    pub fn apply(&self, brightness: f32) -> f32 {
+        let brightness = brightness.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => brightness,
+            Self::Cie => {
+                let lightness = brightness * 100.0;
+                if lightness <= 8.0 {
+                    lightness / 903.3
+                } else {
+                    ((lightness + 16.0) / 116.0).powi(3)
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for BrightnessCurve {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "linear" => Self::Linear,
+            "cie" => Self::Cie,
+            _ => return Err(Error::BrightnessCurve(s.to_string()))
+        })
+    }
+}
+
+impl fmt::Display for BrightnessCurve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Linear => write!(f, "linear"),
+            Self::Cie => write!(f, "cie"),
+        }
+    }
+}
+
+/// Lookup table with the corrected value of every possible input value, per channel.
+/// Values are kept at 16 bits, so dim colors don't lose their hue to rounding.
+/// Rebuilt whenever the correction or brightness changes, so applying it is cheap.
+#[derive(Debug, Clone)]
+pub(crate) struct CorrectionTable([[u16; 256]; 4]);
+
+impl CorrectionTable {
+    pub(crate) fn new(correction: &Correction, brightness: f32) -> Self {
+        let luminance = correction.brightness_curve.apply(brightness);
+        let wb = correction.white_balance;
+
+        // The white channel of RGBW strips is already white, so it isn't balanced
+        let mut table = [[0u16; 256]; 4];
+        for (channel, scale) in [wb.red, wb.green, wb.blue, 1.0].into_iter().enumerate() {
+            for (value, out) in table[channel].iter_mut().enumerate() {
+                let linear = (value as f32 / 255.0).powf(correction.gamma);
+                *out = (linear * scale.clamp(0.0, 1.0) * luminance * 65535.0).round() as u16;
+            }
+        }
+
+        Self(table)
+    }
+
+    pub(crate) fn apply(&self, rgb: Rgb) -> Rgb16 {
+        let Rgb { r, g, b, w } = rgb;
+        Rgb16::new_rgbw(self.0[0][r as usize], self.0[1][g as usize], self.0[2][b as usize], self.0[3][w as usize])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::correction::CorrectionTable;
+    use crate::{BrightnessCurve, Correction, Rgb, WhiteBalance};
+
+    #[test]
+    fn test_default_is_identity() {
+        let table = CorrectionTable::new(&Correction::default(), 1.0);
+        for value in 0..=255 {
+            assert_eq!(Rgb::new(value, value, value), table.apply(Rgb::new(value, value, value)).to_rgb());
+        }
+    }
+
+    #[test]
+    fn test_white_balance() {
+        let correction = Correction {
+            white_balance: WhiteBalance { red: 1.0, green: 0.5, blue: 0.0 },
+            ..Correction::default()
+        };
+        let table = CorrectionTable::new(&correction, 1.0);
+        assert_eq!(Rgb::new(255, 128, 0), table.apply(Rgb::new(255, 255, 255)).to_rgb());
+    }
+
+    #[test]
+    fn test_dim_colors_keep_hue() {
+        let correction = Correction { brightness_curve: BrightnessCurve::Linear, ..Correction::default() };
+        let table = CorrectionTable::new(&correction, 0.01);
+        let dim = table.apply(Rgb::new(255, 100, 0));
+        assert_eq!(655, dim.r);
+        assert_eq!(257, dim.g);
+    }
+
+    #[test]
+    fn test_cie_curve() {
+        assert_eq!(0.0, BrightnessCurve::Cie.apply(0.0));
+        assert!((BrightnessCurve::Cie.apply(0.5) - 0.184).abs() < 0.001);
+        assert!((BrightnessCurve::Cie.apply(1.0) - 1.0).abs() < 0.001);
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{Rgb, Rgb16};
+
+/// Temporal dithering of 16 bit colors down to the 8 bits a strip takes.
+/// Every LED carries the rounding error of each channel over to the next frame,
+/// so averaged over consecutive frames the strip shows the full precision color.
+/// This only works if frames keep being written, see [Dither::is_exact].
+#[derive(Debug, Clone)]
+pub(crate) struct Dither {
+    /// The error carried over per LED and channel, in units of `1 / 65535`
+    error: Vec<[u32; 4]>,
+}
+
+impl Dither {
+    pub(crate) fn new(length: u16) -> Self {
+        // Start every LED at a different error, so neighbouring LEDs don't flicker in step
+        let error = (0..length as u64)
+            .map(|i| [0, 1, 2, 3].map(|channel| ((i * 4 + channel) * 40503 % 65535) as u32))
+            .collect();
+
+        Self {
+            error,
+        }
+    }
+
+    /// Dither the pixels into `out`, replacing its contents
+    pub(crate) fn apply(&mut self, pixels: &[Rgb16], out: &mut Vec<Rgb>) {
+        out.clear();
+        out.extend(pixels.iter()
+            .zip(self.error.iter_mut())
+            .map(|(pixel, error)| {
+                let mut dithered = [0u8; 4];
+                for (channel, value) in pixel.channels().into_iter().enumerate() {
+                    let scaled = value as u32 * 255 + error[channel];
+                    dithered[channel] = (scaled / 65535) as u8;
+                    error[channel] = scaled % 65535;
+                }
+
+                Rgb::new_rgbw(dithered[0], dithered[1], dithered[2], dithered[3])
+            }));
+    }
+
+    /// Whether every channel of every LED can be shown exactly in 8 bits,
+    /// in which case there is nothing to dither
+    pub(crate) fn is_exact(pixels: &[Rgb16]) -> bool {
+        pixels.iter()
+            .flat_map(|x| x.channels())
+            // 65535 is 255 * 257, so every multiple of 257 is an 8 bit value
+            .all(|x| x.is_multiple_of(257))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dither::Dither;
+    use crate::Rgb16;
+
+    #[test]
+    fn test_average_matches_input() {
+        // Just over 1.5 out of 255
+        let pixel = Rgb16::new_rgbw(400, 0, 65535, 0);
+        let mut dither = Dither::new(1);
+
+        let mut out = Vec::new();
+        let frames = (0..1000)
+            .map(|_| {
+                dither.apply(&[pixel], &mut out);
+                out[0]
+            })
+            .collect::<Vec<_>>();
+
+        assert!(frames.iter().all(|x| x.r == 1 || x.r == 2));
+        let average = frames.iter().map(|x| x.r as f32).sum::<f32>() / frames.len() as f32;
+        assert!((average - 400.0 * 255.0 / 65535.0).abs() < 0.01);
+        assert!(frames.iter().all(|x| x.g == 0 && x.b == 255));
+    }
+
+    #[test]
+    fn test_is_exact() {
+        assert!(Dither::is_exact(&[Rgb16::new_rgbw(257, 0, 65535, 514)]));
+        assert!(!Dither::is_exact(&[Rgb16::new_rgbw(258, 0, 0, 0)]));
+    }
+}
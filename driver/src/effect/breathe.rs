@@ -0,0 +1,31 @@
+use std::f32::consts::PI;
+use std::time::Duration;
+use crate::effect::{Effect, EffectParams};
+use crate::{Frame, Rgb};
+
+/// Slowly fades the whole strip in and out, moving to the next color of the palette every breath
+pub struct Breathe {
+    params: EffectParams,
+    palette: Vec<Rgb>,
+}
+
+impl Breathe {
+    /// Seconds a single breath takes at normal speed
+    const PERIOD: f32 = 4.0;
+
+    pub fn new(params: EffectParams) -> Self {
+        Self {
+            palette: params.palette_or(&[Rgb::new(255, 255, 255)]),
+            params,
+        }
+    }
+}
+
+impl Effect for Breathe {
+    fn render(&mut self, elapsed: Duration, frame: &mut Frame) {
+        let phase = self.params.time(elapsed) / Self::PERIOD;
+        let color = self.palette[phase as usize % self.palette.len()];
+        let level = (1.0 - (phase * 2.0 * PI).cos()) / 2.0;
+        frame.fill(color.scale(level * self.params.intensity));
+    }
+}
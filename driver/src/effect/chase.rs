@@ -0,0 +1,42 @@
+use std::time::Duration;
+use crate::effect::{Effect, EffectParams};
+use crate::{Frame, Rgb};
+
+/// Groups of lit LEDs running along the strip, theater marquee style
+pub struct Chase {
+    params: EffectParams,
+    palette: Vec<Rgb>,
+}
+
+impl Chase {
+    /// LEDs lit in every group
+    const WIDTH: f32 = 3.0;
+    /// LEDs from the start of one group to the start of the next
+    const SPACING: f32 = 10.0;
+    /// LEDs moved per second at normal speed
+    const RATE: f32 = 15.0;
+
+    pub fn new(params: EffectParams) -> Self {
+        Self {
+            palette: params.palette_or(&[Rgb::new(255, 255, 255)]),
+            params,
+        }
+    }
+}
+
+impl Effect for Chase {
+    fn render(&mut self, elapsed: Duration, frame: &mut Frame) {
+        let offset = self.params.time(elapsed) * Self::RATE;
+        for (i, pixel) in frame.pixels_mut().iter_mut().enumerate() {
+            let position = i as f32 - offset;
+            *pixel = if position.rem_euclid(Self::SPACING) < Self::WIDTH {
+                // Every group gets the next color from the palette
+                let group = (position / Self::SPACING).floor() as i64;
+                let color = self.palette[group.rem_euclid(self.palette.len() as i64) as usize];
+                color.scale(self.params.intensity)
+            } else {
+                Rgb::off()
+            };
+        }
+    }
+}
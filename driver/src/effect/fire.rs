@@ -0,0 +1,155 @@
+use std::time::Duration;
+use crate::effect::{Effect, EffectParams, Random};
+use crate::{Frame, Rgb};
+
+/// Flickering flames rising from the start of the strip.
+/// Based on Mark Kriegsman's Fire2012. A palette replaces the colors of the flames,
+/// running from the coolest to the hottest.
+pub struct Fire {
+    params: EffectParams,
+    random: Random,
+    heat: Vec<u8>,
+    steps: u64,
+}
+
+impl Fire {
+    /// Simulation steps per second at normal speed
+    const RATE: f32 = 30.0;
+    /// How much the air cools as it rises
+    const COOLING: u32 = 55;
+    /// Chance out of 255 for a new spark every step
+    const SPARKING: u32 = 120;
+
+    pub fn new(params: EffectParams) -> Self {
+        Self {
+            params,
+            random: Random::new(),
+            heat: Vec::new(),
+            steps: 0,
+        }
+    }
+
+    /// How much a cell cools at most in one step. Short strips cool faster,
+    /// but never by more than all the heat a cell can hold.
+    fn max_cooldown(length: usize) -> u32 {
+        (Self::COOLING * 10 / length.max(1) as u32 + 2).min(u8::MAX as u32 + 1)
+    }
+
+    fn step(&mut self) {
+        let length = self.heat.len();
+
+        let max_cooldown = Self::max_cooldown(length);
+        for heat in self.heat.iter_mut() {
+            let cooldown = self.random.below(max_cooldown);
+            *heat = heat.saturating_sub(cooldown as u8);
+        }
+
+        // Heat drifts up and diffuses
+        for i in (2..length).rev() {
+            self.heat[i] = ((self.heat[i - 1] as u32 + 2 * self.heat[i - 2] as u32) / 3) as u8;
+        }
+
+        if self.random.below(255) < Self::SPARKING {
+            let i = self.random.below(length.min(7) as u32) as usize;
+            self.heat[i] = self.heat[i].saturating_add(160 + self.random.below(95) as u8);
+        }
+    }
+
+    fn heat_color(&self, heat: u8) -> Rgb {
+        let color = match self.params.palette.as_slice() {
+            [] => Self::flame_color(heat),
+            // Cold cells are off, the hottest ones show the last color
+            palette => {
+                let position = heat as f32 / 255.0 * palette.len() as f32;
+                let index = position as usize;
+                let from = if index == 0 { Rgb::off() } else { palette[index - 1] };
+                let to = palette[index.min(palette.len() - 1)];
+                from.lerp(to, position.fract())
+            }
+        };
+
+        color.scale(self.params.intensity)
+    }
+
+    /// The colors of Fire2012, from black through red and yellow to white
+    fn flame_color(heat: u8) -> Rgb {
+        // Scale down to 0..=191, split into three 64 step ramps
+        let t192 = (heat as u32 * 191 / 255) as u8;
+        let ramp = (t192 & 0x3F) << 2;
+        if t192 & 0x80 != 0 {
+            Rgb::new(255, 255, ramp)
+        } else if t192 & 0x40 != 0 {
+            Rgb::new(255, ramp, 0)
+        } else {
+            Rgb::new(ramp, 0, 0)
+        }
+    }
+}
+
+impl Effect for Fire {
+    fn render(&mut self, elapsed: Duration, frame: &mut Frame) {
+        if frame.is_empty() {
+            return;
+        }
+
+        if self.heat.len() != frame.len() {
+            self.heat = vec![0; frame.len()];
+        }
+
+        let target = (self.params.time(elapsed) * Self::RATE) as u64;
+        // Don't try to catch up after a long pause
+        self.steps = self.steps.max(target.saturating_sub(10));
+        while self.steps < target {
+            self.step();
+            self.steps += 1;
+        }
+
+        for (pixel, &heat) in frame.pixels_mut().iter_mut().zip(&self.heat) {
+            *pixel = self.heat_color(heat);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use crate::effect::fire::Fire;
+    use crate::effect::Effect;
+    use crate::{EffectParams, Frame, Rgb};
+
+    #[test]
+    fn test_short_strips_flicker() {
+        for length in 1..=3 {
+            let mut fire = Fire::new(EffectParams::default());
+            fire.heat = vec![0; length];
+
+            let (mut lit, mut saturated) = (0, 0);
+            for _ in 0..1000 {
+                fire.step();
+                lit += fire.heat.iter().any(|&x| x > 0) as u32;
+                saturated += fire.heat.iter().all(|&x| x == u8::MAX) as u32;
+            }
+
+            // Sparks keep coming, but cool down again rather than piling up
+            assert!(lit > 250, "{length} LEDs were only lit for {lit} of 1000 steps");
+            assert!(saturated < 500, "{length} LEDs were at full heat for {saturated} of 1000 steps");
+        }
+    }
+
+    #[test]
+    fn test_palette_and_intensity() {
+        let mut fire = Fire::new(EffectParams {
+            palette: vec![Rgb::new(0, 0, 255)],
+            intensity: 0.5,
+            ..EffectParams::default()
+        });
+
+        let mut frame = Frame::new(30);
+        for i in 0..100 {
+            fire.render(Duration::from_millis(i * 16), &mut frame);
+        }
+
+        assert!(frame.pixels().iter().any(|x| x.b > 0));
+        assert!(frame.pixels().iter().all(|x| x.r == 0 && x.g == 0 && x.b <= 128));
+    }
+}
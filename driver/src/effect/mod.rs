@@ -0,0 +1,185 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::{Serialize, Deserialize};
+use crate::error::Error;
+use crate::{Frame, Rgb};
+
+mod breathe;
+mod chase;
+mod fire;
+mod rainbow;
+mod twinkle;
+mod wipe;
+
+pub use breathe::*;
+pub use chase::*;
+pub use fire::*;
+pub use rainbow::*;
+pub use twinkle::*;
+pub use wipe::*;
+
+/// The frame rate effects are rendered at, unless configured otherwise
+pub const DEFAULT_FPS: u32 = 60;
+
+/// An animation rendered frame by frame
+pub trait Effect: Send {
+    /// Render the effect as it looks `elapsed` after it was started.
+    /// The frame holds the previously rendered frame, so effects may build on it.
+    fn render(&mut self, elapsed: Duration, frame: &mut Frame);
+}
+
+/// The built-in effects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EffectKind {
+    Rainbow,
+    Breathe,
+    Chase,
+    Twinkle,
+    Fire,
+    ColorWipe,
+}
+
+impl EffectKind {
+    pub fn build(&self, params: &EffectParams) -> Box<dyn Effect> {
+        let params = params.clone();
+        match self {
+            Self::Rainbow => Box::new(Rainbow::new(params)),
+            Self::Breathe => Box::new(Breathe::new(params)),
+            Self::Chase => Box::new(Chase::new(params)),
+            Self::Twinkle => Box::new(Twinkle::new(params)),
+            Self::Fire => Box::new(Fire::new(params)),
+            Self::ColorWipe => Box::new(ColorWipe::new(params)),
+        }
+    }
+}
+
+impl FromStr for EffectKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "rainbow" => Self::Rainbow,
+            "breathe" => Self::Breathe,
+            "chase" => Self::Chase,
+            "twinkle" => Self::Twinkle,
+            "fire" => Self::Fire,
+            "color-wipe" | "wipe" => Self::ColorWipe,
+            _ => return Err(Error::Effect(s.to_string()))
+        })
+    }
+}
+
+impl fmt::Display for EffectKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Rainbow => "rainbow",
+            Self::Breathe => "breathe",
+            Self::Chase => "chase",
+            Self::Twinkle => "twinkle",
+            Self::Fire => "fire",
+            Self::ColorWipe => "color-wipe",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Parameters shared by all effects
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EffectParams {
+    /// Multiplier for how fast the effect runs, `1.0` is the effect's normal speed
+    pub speed: f32,
+    /// The colors used by the effect. If empty, the effect uses its own colors.
+    pub palette: Vec<Rgb>,
+    /// How bright the effect is, in the range `0.0..=1.0`
+    pub intensity: f32,
+}
+
+impl Default for EffectParams {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            palette: Vec::new(),
+            intensity: 1.0,
+        }
+    }
+}
+
+impl EffectParams {
+    /// The palette, or `default` if no palette was configured
+    pub(crate) fn palette_or(&self, default: &[Rgb]) -> Vec<Rgb> {
+        if self.palette.is_empty() {
+            default.to_vec()
+        } else {
+            self.palette.clone()
+        }
+    }
+
+    /// Seconds elapsed, adjusted for the effect's speed
+    pub(crate) fn time(&self, elapsed: Duration) -> f32 {
+        elapsed.as_secs_f32() * self.speed
+    }
+}
+
+/// Small and fast xorshift generator, random enough for effects
+pub(crate) struct Random(u32);
+
+impl Random {
+    pub(crate) fn new() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.subsec_nanos())
+            .unwrap_or(0);
+        // Xorshift is stuck at 0 forever
+        Self(seed | 1)
+    }
+
+    pub(crate) fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    /// A random number in `0..max`
+    pub(crate) fn below(&mut self, max: u32) -> u32 {
+        if max == 0 {
+            0
+        } else {
+            self.next() % max
+        }
+    }
+
+    /// A random number in `0.0..1.0`
+    pub(crate) fn unit(&mut self) -> f32 {
+        (self.next() >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use crate::{EffectKind, EffectParams, Frame};
+
+    #[test]
+    fn test_effects_fill_frame() {
+        let kinds = [EffectKind::Rainbow, EffectKind::Breathe, EffectKind::Chase, EffectKind::Twinkle, EffectKind::Fire, EffectKind::ColorWipe];
+        for kind in kinds {
+            let mut effect = kind.build(&EffectParams::default());
+            let mut frame = Frame::new(30);
+            for i in 0..100 {
+                effect.render(Duration::from_millis(i * 16), &mut frame);
+            }
+            assert_eq!(30, frame.len(), "{kind} changed the frame length");
+        }
+    }
+
+    #[test]
+    fn test_parse_kind() {
+        assert_eq!(EffectKind::ColorWipe, "color-wipe".parse().unwrap());
+        assert_eq!("color-wipe", EffectKind::ColorWipe.to_string());
+        assert!("strobe".parse::<EffectKind>().is_err());
+    }
+}
@@ -0,0 +1,28 @@
+use std::time::Duration;
+use crate::effect::{Effect, EffectParams};
+use crate::{Frame, Rgb};
+
+/// A rainbow spread over the strip, slowly moving along it
+pub struct Rainbow {
+    params: EffectParams,
+}
+
+impl Rainbow {
+    pub fn new(params: EffectParams) -> Self {
+        Self {
+            params
+        }
+    }
+}
+
+impl Effect for Rainbow {
+    fn render(&mut self, elapsed: Duration, frame: &mut Frame) {
+        let length = frame.len() as f32;
+        // One full cycle through the colors every 10 seconds
+        let offset = self.params.time(elapsed) * 36.0;
+        for (i, pixel) in frame.pixels_mut().iter_mut().enumerate() {
+            let hue = i as f32 / length * 360.0 + offset;
+            *pixel = Rgb::from_hsv(hue, 1.0, self.params.intensity);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::time::Duration;
+use crate::effect::{Effect, EffectParams, Random};
+use crate::{Frame, Rgb};
+
+/// Random LEDs light up and slowly fade out again
+pub struct Twinkle {
+    params: EffectParams,
+    palette: Vec<Rgb>,
+    random: Random,
+    levels: Vec<f32>,
+    colors: Vec<Rgb>,
+    last: Duration,
+}
+
+impl Twinkle {
+    /// Chance per second for an LED to light up at normal speed
+    const CHANCE: f32 = 0.3;
+    /// How fast lit LEDs fade out at normal speed
+    const DECAY: f32 = 2.0;
+
+    pub fn new(params: EffectParams) -> Self {
+        Self {
+            palette: params.palette_or(&[Rgb::new(255, 255, 255)]),
+            params,
+            random: Random::new(),
+            levels: Vec::new(),
+            colors: Vec::new(),
+            last: Duration::ZERO,
+        }
+    }
+}
+
+impl Effect for Twinkle {
+    fn render(&mut self, elapsed: Duration, frame: &mut Frame) {
+        if self.levels.len() != frame.len() {
+            self.levels = vec![0.0; frame.len()];
+            self.colors = vec![Rgb::off(); frame.len()];
+        }
+
+        let delta = self.params.time(elapsed.saturating_sub(self.last));
+        self.last = elapsed;
+
+        let decay = (-delta * Self::DECAY).exp();
+        for (i, pixel) in frame.pixels_mut().iter_mut().enumerate() {
+            self.levels[i] *= decay;
+            if self.random.unit() < delta * Self::CHANCE {
+                self.levels[i] = 1.0;
+                self.colors[i] = self.palette[self.random.below(self.palette.len() as u32) as usize];
+            }
+
+            *pixel = self.colors[i].scale(self.levels[i] * self.params.intensity);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use std::time::Duration;
+use crate::effect::{Effect, EffectParams};
+use crate::{Frame, Rgb};
+
+/// Fills the strip LED by LED with each color of the palette in turn
+pub struct ColorWipe {
+    params: EffectParams,
+    palette: Vec<Rgb>,
+}
+
+impl ColorWipe {
+    /// Seconds a single wipe takes at normal speed
+    const PERIOD: f32 = 2.0;
+
+    pub fn new(params: EffectParams) -> Self {
+        Self {
+            palette: params.palette_or(&[Rgb::new(255, 0, 0), Rgb::new(0, 255, 0), Rgb::new(0, 0, 255)]),
+            params,
+        }
+    }
+}
+
+impl Effect for ColorWipe {
+    fn render(&mut self, elapsed: Duration, frame: &mut Frame) {
+        let phase = self.params.time(elapsed) / Self::PERIOD;
+        let wipe = phase as usize;
+        let color = self.palette[wipe % self.palette.len()].scale(self.params.intensity);
+        let previous = self.palette[(wipe + self.palette.len() - 1) % self.palette.len()].scale(self.params.intensity);
+
+        let lit = (phase.fract() * frame.len() as f32) as usize;
+        for (i, pixel) in frame.pixels_mut().iter_mut().enumerate() {
+            *pixel = if i < lit { color } else { previous };
+        }
+    }
+}
@@ -0,0 +1,399 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use crate::error::Error;
+use crate::{Chipset, Rgb};
+
+/// The order in which a strip expects the color channels of each LED.
+/// This differs between WS281x variants and manufacturers.
+/// The white channel of RGBW strips is always sent last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorOrder {
+    Rgb,
+    Rbg,
+    Grb,
+    Gbr,
+    /// The order used by the strips this project was originally written for
+    #[default]
+    Brg,
+    Bgr,
+}
+
+impl ColorOrder {
+    /// Reorder the color's channels into the order in which they are sent to the strip
+    pub fn apply(&self, rgb: Rgb) -> [u8; 3] {
+        self.reorder([rgb.r, rgb.g, rgb.b])
+    }
+
+    /// Reorder red, green and blue channels of any kind into the order in which they are sent to the strip
+    pub fn reorder<T>(&self, [r, g, b]: [T; 3]) -> [T; 3] {
+        match self {
+            Self::Rgb => [r, g, b],
+            Self::Rbg => [r, b, g],
+            Self::Grb => [g, r, b],
+            Self::Gbr => [g, b, r],
+            Self::Brg => [b, r, g],
+            Self::Bgr => [b, g, r],
+        }
+    }
+}
+
+impl FromStr for ColorOrder {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "rgb" => Self::Rgb,
+            "rbg" => Self::Rbg,
+            "grb" => Self::Grb,
+            "gbr" => Self::Gbr,
+            "brg" => Self::Brg,
+            "bgr" => Self::Bgr,
+            _ => return Err(Error::ColorOrder(s.to_string()))
+        })
+    }
+}
+
+impl fmt::Display for ColorOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Rgb => "rgb",
+            Self::Rbg => "rbg",
+            Self::Grb => "grb",
+            Self::Gbr => "gbr",
+            Self::Brg => "brg",
+            Self::Bgr => "bgr",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// How the white channel of an RGBW strip is derived from a color
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "mode")]
+pub enum WhiteExtraction {
+    /// Only the color's explicit white channel is used
+    None,
+    /// The part shared by all three channels is moved to the white channel
+    Min,
+    /// Like [WhiteExtraction::Min], but takes the tint of the white LED into account.
+    /// `kelvin` is the color temperature of the white LED, e.g. `4500` for 'natural white' strips.
+    Temperature { kelvin: u16 },
+}
+
+impl WhiteExtraction {
+    /// Move as much of the color as possible to the white channel
+    pub fn apply(&self, rgb: Rgb) -> Rgb {
+        let white = match self {
+            Self::None => return rgb,
+            Self::Min => Rgb::new(255, 255, 255),
+            Self::Temperature { kelvin } => Rgb::from_kelvin(*kelvin),
+        };
+
+        // How far the white LED can be turned on before it would
+        // contribute more to a channel than the color asks for
+        let ratio = |value: u8, white: u8| if white == 0 {
+            u32::MAX
+        } else {
+            value as u32 * 255 / white as u32
+        };
+        let w = ratio(rgb.r, white.r)
+            .min(ratio(rgb.g, white.g))
+            .min(ratio(rgb.b, white.b))
+            .min(255);
+
+        let subtract = |value: u8, white: u8| value - (w * white as u32 / 255) as u8;
+        Rgb::new_rgbw(
+            subtract(rgb.r, white.r),
+            subtract(rgb.g, white.g),
+            subtract(rgb.b, white.b),
+            rgb.w.saturating_add(w as u8),
+        )
+    }
+}
+
+impl FromStr for WhiteExtraction {
+    type Err = Error;
+
+    /// Parses `none`, `min` or a white LED temperature like `4500K`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_lowercase();
+        Ok(match lower.as_str() {
+            "none" => Self::None,
+            "min" => Self::Min,
+            _ => {
+                let kelvin = lower.strip_suffix('k')
+                    .and_then(|x| x.parse().ok())
+                    .ok_or_else(|| Error::WhiteExtraction(s.to_string()))?;
+                Self::Temperature { kelvin }
+            }
+        })
+    }
+}
+
+/// How the pixels are laid out on the wire
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PixelFormat {
+    pub chipset: Chipset,
+    pub color_order: ColorOrder,
+    /// Set for RGBW strips, like the SK6812 RGBW. `None` for RGB strips.
+    pub white: Option<WhiteExtraction>,
+}
+
+impl PixelFormat {
+    pub fn channels(&self) -> usize {
+        match self.white {
+            Some(_) => 4,
+            None => 3,
+        }
+    }
+}
+
+/// SPI bit patterns making up WS28xx data bits at a given SPI clock speed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Ws28xxTiming {
+    /// SPI bits per data bit
+    bits: u32,
+    /// SPI bits the line is high for a 0
+    zero_high: u32,
+    /// SPI bits the line is high for a 1
+    one_high: u32,
+    /// Bytes of low signal after a frame, so the LEDs latch it
+    reset_bytes: usize,
+}
+
+impl Ws28xxTiming {
+    /// The slowest clock at which we can still get the high times right
+    const MIN_SPEED_HZ: u32 = 2_000_000;
+    /// The fastest clock at which a data bit still fits our bit buffer
+    const MAX_SPEED_HZ: u32 = 38_000_000;
+
+    pub(crate) fn new(speed_hz: u32) -> Result<Self, Error> {
+        if !(Self::MIN_SPEED_HZ..=Self::MAX_SPEED_HZ).contains(&speed_hz) {
+            return Err(Error::SpiSpeed(speed_hz));
+        }
+
+        // A data bit takes 1.25µs. The high times of 0.35µs and 0.7µs
+        // are within spec for both the WS2812 and SK6812
+        let spi_bits = |seconds: f64| (speed_hz as f64 * seconds).round() as u32;
+        let zero_high = spi_bits(0.35e-6).max(1);
+        Ok(Self {
+            bits: spi_bits(1.25e-6).max(3),
+            zero_high,
+            one_high: spi_bits(0.7e-6).max(zero_high + 1),
+            // Newer LEDs need at least 280µs of low signal
+            reset_bytes: (spi_bits(300e-6) / 8 + 1) as usize,
+        })
+    }
+
+    /// The SPI bits representing a single data bit, in the lowest `self.bits` bits
+    fn pattern(&self, bit: bool) -> u64 {
+        let high = if bit { self.one_high } else { self.zero_high };
+        ((1u64 << high) - 1) << (self.bits - high)
+    }
+}
+
+/// Packs SPI bit patterns of arbitrary length into bytes
+struct BitWriter<'a> {
+    out: &'a mut Vec<u8>,
+    buffer: u64,
+    buffered: u32,
+}
+
+impl<'a> BitWriter<'a> {
+    fn new(out: &'a mut Vec<u8>) -> Self {
+        Self {
+            out,
+            buffer: 0,
+            buffered: 0,
+        }
+    }
+
+    fn push(&mut self, bits: u64, count: u32) {
+        self.buffer = (self.buffer << count) | bits;
+        self.buffered += count;
+        while self.buffered >= 8 {
+            self.buffered -= 8;
+            self.out.push((self.buffer >> self.buffered) as u8);
+        }
+    }
+
+    /// Write out the remaining bits, padded with zeroes
+    fn finish(self) {
+        if self.buffered > 0 {
+            self.out.push((self.buffer << (8 - self.buffered)) as u8);
+        }
+    }
+}
+
+fn encode_byte(byte: u8, timing: &Ws28xxTiming, writer: &mut BitWriter) {
+    for bit in (0..8).rev() {
+        writer.push(timing.pattern(byte & (1 << bit) != 0), timing.bits);
+    }
+}
+
+/// Encodes frames to the SPI bit stream understood by WS28xx and SK6812 LEDs,
+/// followed by the reset signal.
+/// A data byte always takes `timing.bits` whole bytes of SPI data, so these are looked up
+/// in a table rather than computed, and only pixels which changed since the previous frame are encoded again.
+#[derive(Debug, Clone)]
+pub(crate) struct Ws28xxEncoder {
+    timing: Ws28xxTiming,
+    /// The SPI bytes for every value of a data byte, `timing.bits` bytes each
+    table: Vec<u8>,
+    /// The channels of every pixel in the encoded frame, as they are sent
+    previous: Vec<[u8; 4]>,
+}
+
+impl Ws28xxEncoder {
+    pub(crate) fn new(timing: Ws28xxTiming) -> Self {
+        let mut table = Vec::with_capacity(256 * timing.bits as usize);
+        let mut writer = BitWriter::new(&mut table);
+        for byte in 0..=u8::MAX {
+            encode_byte(byte, &timing, &mut writer);
+        }
+        writer.finish();
+
+        Self {
+            timing,
+            table,
+            previous: Vec::new(),
+        }
+    }
+
+    /// Encode the pixels into `out`, which must hold the previous frame encoded by this encoder
+    pub(crate) fn encode<I: ExactSizeIterator<Item = Rgb>>(&mut self, pixels: I, format: PixelFormat, out: &mut Vec<u8>) {
+        let bits = self.timing.bits as usize;
+        let channels = format.channels();
+        let stride = channels * bits;
+        let size = pixels.len() * stride + self.timing.reset_bytes;
+
+        // Anything else in the buffer can't be reused
+        let full = out.len() != size || self.previous.len() != pixels.len();
+        if full {
+            out.clear();
+            out.resize(size, 0);
+            self.previous.clear();
+            self.previous.resize(pixels.len(), [0; 4]);
+        }
+
+        for (i, rgb) in pixels.enumerate() {
+            let sent = match format.white {
+                Some(white) => {
+                    let rgbw = white.apply(rgb);
+                    let [a, b, c] = format.color_order.apply(rgbw);
+                    [a, b, c, rgbw.w]
+                },
+                None => {
+                    let [a, b, c] = format.color_order.apply(rgb);
+                    [a, b, c, 0]
+                }
+            };
+
+            if !full && self.previous[i] == sent {
+                continue;
+            }
+            self.previous[i] = sent;
+
+            let pixel = &mut out[i * stride..(i + 1) * stride];
+            for (chunk, &byte) in pixel.chunks_exact_mut(bits).zip(&sent[..channels]) {
+                let start = byte as usize * bits;
+                chunk.copy_from_slice(&self.table[start..start + bits]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::encoding::{BitWriter, encode_byte, Ws28xxEncoder, Ws28xxTiming};
+    use crate::{Chipset, ColorOrder, PixelFormat, Rgb, WhiteExtraction};
+
+    #[test]
+    fn test_apply() {
+        let rgb = Rgb::new(1, 2, 3);
+        assert_eq!([1, 2, 3], ColorOrder::Rgb.apply(rgb));
+        assert_eq!([2, 1, 3], ColorOrder::Grb.apply(rgb));
+        assert_eq!([3, 1, 2], ColorOrder::Brg.apply(rgb));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(ColorOrder::Gbr, "GBR".parse().unwrap());
+        assert!("rgbw".parse::<ColorOrder>().is_err());
+        assert_eq!(WhiteExtraction::Temperature { kelvin: 4500 }, "4500K".parse().unwrap());
+    }
+
+    #[test]
+    fn test_min_extraction() {
+        assert_eq!(Rgb::new_rgbw(200, 50, 0, 55), WhiteExtraction::Min.apply(Rgb::new(255, 105, 55)));
+        assert_eq!(Rgb::new_rgbw(0, 0, 0, 255), WhiteExtraction::Min.apply(Rgb::new(255, 255, 255)));
+    }
+
+    #[test]
+    fn test_temperature_extraction() {
+        // A warm white LED can't produce pure white on its own, blue has to make up for it
+        let rgbw = WhiteExtraction::Temperature { kelvin: 3000 }.apply(Rgb::new(255, 255, 255));
+        assert_eq!(255, rgbw.w);
+        assert_eq!(0, rgbw.r);
+        assert!(rgbw.b > 0);
+    }
+
+    #[test]
+    fn test_timing() {
+        let timing = Ws28xxTiming::new(15_600_000).unwrap();
+        assert_eq!(Ws28xxTiming { bits: 20, zero_high: 5, one_high: 11, reset_bytes: 586 }, timing);
+        assert!(Ws28xxTiming::new(1_000_000).is_err());
+    }
+
+    #[test]
+    fn test_encode_rgbw() {
+        // At 2.4 MHz every data bit is 3 SPI bits, 0b100 for a 0 and 0b110 for a 1
+        let timing = Ws28xxTiming::new(2_400_000).unwrap();
+        let format = PixelFormat { chipset: Chipset::Ws28xx, color_order: ColorOrder::Grb, white: Some(WhiteExtraction::None) };
+        let mut bits = Vec::new();
+        Ws28xxEncoder::new(timing).encode([Rgb::new_rgbw(0, 0, 0, 0x80)].into_iter(), format, &mut bits);
+
+        assert_eq!(4 * 3 + timing.reset_bytes, bits.len());
+        assert_eq!([0b1001_0010, 0b0100_1001, 0b0010_0100], bits[0..3]);
+        assert_eq!([0b1101_0010, 0b0100_1001, 0b0010_0100], bits[9..12]);
+    }
+
+    #[test]
+    fn test_table_matches_bits() {
+        // 15.6 MHz doesn't divide a data bit into a whole number of SPI bytes
+        let timing = Ws28xxTiming::new(15_600_000).unwrap();
+        let format = PixelFormat { chipset: Chipset::Ws28xx, color_order: ColorOrder::Rgb, white: None };
+
+        let mut expected = Vec::new();
+        let mut writer = BitWriter::new(&mut expected);
+        [0x12, 0x34, 0xAB].iter().for_each(|&x| encode_byte(x, &timing, &mut writer));
+        writer.finish();
+
+        let mut bits = Vec::new();
+        Ws28xxEncoder::new(timing).encode([Rgb::new(0x12, 0x34, 0xAB)].into_iter(), format, &mut bits);
+        assert_eq!(expected, bits[..expected.len()]);
+    }
+
+    #[test]
+    fn test_encode_changed_pixels() {
+        let timing = Ws28xxTiming::new(2_400_000).unwrap();
+        let format = PixelFormat { chipset: Chipset::Ws28xx, color_order: ColorOrder::Grb, white: None };
+        let first = [Rgb::new(255, 0, 0), Rgb::new(0, 255, 0), Rgb::new(0, 0, 255)];
+        let second = [Rgb::new(255, 0, 0), Rgb::new(1, 2, 3), Rgb::new(0, 0, 255)];
+
+        let mut encoder = Ws28xxEncoder::new(timing);
+        let mut bits = Vec::new();
+        encoder.encode(first.into_iter(), format, &mut bits);
+        encoder.encode(second.into_iter(), format, &mut bits);
+
+        let mut expected = Vec::new();
+        Ws28xxEncoder::new(timing).encode(second.into_iter(), format, &mut expected);
+        assert_eq!(expected, bits);
+
+        // A different length starts over
+        encoder.encode(second[..1].iter().copied(), format, &mut bits);
+        assert_eq!(3 * 3 + timing.reset_bytes, bits.len());
+    }
+}
@@ -0,0 +1,648 @@
+use std::sync::{Arc, mpsc, Mutex};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+use crate::effect::{Effect, EffectKind, EffectParams};
+use crate::error::{Error, Result};
+use crate::transition::Transition;
+use crate::{Color, Driver, Frame, Rgb};
+
+enum Command {
+    Rgb(Rgb),
+    Kelvin(u16),
+    ZoneRgb(String, Rgb),
+    Frame(Frame),
+    Brightness(f32),
+    StartEffect(Box<dyn Effect>),
+    StopEffect,
+    SetTransition(Transition),
+    /// A change faded in with its own transition rather than the one set
+    WithTransition(Transition, Box<Command>),
+    SetRefreshInterval(Option<Duration>),
+    /// Reply once no transition is in progress
+    WaitForTransition(mpsc::Sender<()>),
+    /// Reply once every earlier command has been written to the strip
+    Flush(mpsc::Sender<Result<()>>),
+}
+
+/// Handle to a thread owning a [Driver], rendering effects and transitions at a fixed frame rate.
+/// Changes are written with the next frame. If more changes arrive before then,
+/// only the latest is shown rather than every one of them in turn.
+/// Cloning the handle is cheap, the thread stops once all handles are dropped.
+#[derive(Debug, Clone)]
+pub struct Engine {
+    tx: mpsc::Sender<Command>,
+    effect: Arc<Mutex<Option<EffectKind>>>,
+    /// Fades changes in with this rather than the transition set, see [Engine::with_transition]
+    transition: Option<Transition>,
+}
+
+impl Engine {
+    /// Spawn the thread. Changes are applied immediately until a [Transition] is set.
+    pub fn spawn(driver: Driver, fps: u32) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let interval = Duration::from_secs(1) / fps.max(1);
+        thread::Builder::new()
+            .name("deskled-engine".to_string())
+            .spawn(move || Renderer::new(driver, interval).run(rx))
+            .expect("Spawning engine thread");
+
+        Self {
+            tx,
+            effect: Arc::default(),
+            transition: None,
+        }
+    }
+
+    /// A handle to the same thread, fading in the changes in color and brightness made through it
+    /// with `transition` rather than the transition set. Other handles are not affected.
+    pub fn with_transition(&self, transition: Transition) -> Self {
+        Self {
+            transition: Some(transition),
+            ..self.clone()
+        }
+    }
+
+    fn send(&self, command: Command, effect: Option<EffectKind>) -> Result<()> {
+        let command = match (self.transition, command) {
+            (Some(transition), command @ (Command::Rgb(_) | Command::Kelvin(_) | Command::ZoneRgb(..) | Command::Frame(_) | Command::Brightness(_))) => {
+                Command::WithTransition(transition, Box::new(command))
+            },
+            (_, command) => command,
+        };
+
+        // Locked while sending, so the effect changes in the order the commands arrive.
+        // An engine which has stopped never runs the effect.
+        let mut current = self.effect.lock().unwrap();
+        self.tx.send(command).map_err(|_| Error::EngineStopped)?;
+        *current = effect;
+        Ok(())
+    }
+
+    /// Fade every LED on the strip to the same color, stopping any running effect
+    pub fn set_rgb(&self, rgb: Rgb) -> Result<()> {
+        self.send(Command::Rgb(rgb), None)
+    }
+
+    /// Fade every LED on the strip to a color temperature, stopping any running effect.
+    /// The temperature is converted with the driver's [crate::KelvinCalibration].
+    pub fn set_kelvin(&self, kelvin: u16) -> Result<()> {
+        self.send(Command::Kelvin(kelvin), None)
+    }
+
+    /// See [Engine::set_rgb] and [Engine::set_kelvin]
+    pub fn set_color(&self, color: Color) -> Result<()> {
+        match color {
+            Color::Rgb(rgb) => self.set_rgb(rgb),
+            Color::Kelvin(kelvin) => self.set_kelvin(kelvin),
+        }
+    }
+
+    /// Fade every LED in a zone to the same color, stopping any running effect
+    pub fn set_zone_rgb(&self, zone: &str, rgb: Rgb) -> Result<()> {
+        self.send(Command::ZoneRgb(zone.to_string(), rgb), None)
+    }
+
+    /// Fade to a color for every individual LED, stopping any running effect
+    pub fn write_frame(&self, frame: Frame) -> Result<()> {
+        self.send(Command::Frame(frame), None)
+    }
+
+    /// Fade to a brightness level, in the range `0.0..=1.0`
+    pub fn set_brightness(&self, brightness: f32) -> Result<()> {
+        let effect = self.effect();
+        self.send(Command::Brightness(brightness), effect)
+    }
+
+    /// Start rendering an effect, replacing any running effect
+    pub fn start_effect(&self, kind: EffectKind, params: &EffectParams) -> Result<()> {
+        self.send(Command::StartEffect(kind.build(params)), Some(kind))
+    }
+
+    /// Stop the running effect. The strip keeps showing the last rendered frame.
+    pub fn stop_effect(&self) -> Result<()> {
+        self.send(Command::StopEffect, None)
+    }
+
+    /// Set how future changes in color and brightness are faded in.
+    /// A fade already in progress keeps its transition.
+    pub fn set_transition(&self, transition: Transition) -> Result<()> {
+        let effect = self.effect();
+        self.send(Command::SetTransition(transition), effect)
+    }
+
+    /// Rewrite the current frame at this interval while nothing changes,
+    /// so a strip recovers from glitches like a brown-out or a loose connection.
+    /// `None`, the default, only writes frames when something changes.
+    pub fn set_refresh_interval(&self, interval: Option<Duration>) -> Result<()> {
+        let effect = self.effect();
+        self.send(Command::SetRefreshInterval(interval), effect)
+    }
+
+    /// Block until every change made so far has been written to the strip.
+    /// Returns the error if a change could not be applied or writing failed since the last call.
+    pub fn flush(&self) -> Result<()> {
+        let (tx, rx) = mpsc::channel();
+        let effect = self.effect();
+        self.send(Command::Flush(tx), effect)?;
+        rx.recv().map_err(|_| Error::EngineStopped)?
+    }
+
+    /// Block until the transition in progress, if any, has finished
+    pub fn wait_for_transition(&self) -> Result<()> {
+        let (tx, rx) = mpsc::channel();
+        let effect = self.effect();
+        self.send(Command::WaitForTransition(tx), effect)?;
+        rx.recv().map_err(|_| Error::EngineStopped)
+    }
+
+    /// The effect currently running, if any
+    pub fn effect(&self) -> Option<EffectKind> {
+        *self.effect.lock().unwrap()
+    }
+}
+
+struct Fade {
+    from: Frame,
+    to: Frame,
+    from_brightness: f32,
+    to_brightness: f32,
+    started: Instant,
+    transition: Transition,
+}
+
+struct Running {
+    effect: Box<dyn Effect>,
+    started: Instant,
+}
+
+enum State {
+    Idle,
+    Fading(Fade),
+    Effect(Running),
+}
+
+/// The state living on the engine thread
+struct Renderer {
+    driver: Driver,
+    interval: Duration,
+    transition: Transition,
+    state: State,
+    next_frame: Instant,
+    refresh_interval: Option<Duration>,
+    last_write: Instant,
+    /// Whether a change has not been written yet
+    pending: bool,
+    /// Whether anything has been written yet. Until then the strip keeps showing
+    /// what it showed before, rather than the driver's empty frame.
+    shown: bool,
+    /// The last error which has not been reported by [Engine::flush] yet
+    error: Option<String>,
+    waiting: Vec<mpsc::Sender<()>>,
+    flushing: Vec<mpsc::Sender<Result<()>>>,
+}
+
+impl Renderer {
+    fn new(driver: Driver, interval: Duration) -> Self {
+        Self {
+            driver,
+            interval,
+            transition: Transition::NONE,
+            state: State::Idle,
+            next_frame: Instant::now(),
+            refresh_interval: None,
+            last_write: Instant::now(),
+            pending: false,
+            shown: false,
+            error: None,
+            waiting: Vec::new(),
+            flushing: Vec::new(),
+        }
+    }
+
+    fn run(mut self, rx: Receiver<Command>) {
+        let mut stopping = false;
+        while !stopping {
+            let command = match self.deadline() {
+                Some(deadline) => match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(x) => Some(x),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => {
+                        stopping = true;
+                        None
+                    },
+                },
+                // Nothing left to write
+                None => match rx.recv() {
+                    Ok(x) => Some(x),
+                    Err(_) => break,
+                }
+            };
+
+            // Apply everything queued up before writing, so only the latest state is shown
+            if let Some(command) = command {
+                self.handle_and_report(command);
+                loop {
+                    match rx.try_recv() {
+                        Ok(x) => self.handle_and_report(x),
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => {
+                            stopping = true;
+                            break;
+                        },
+                    }
+                }
+            }
+
+            if stopping {
+                // Still write what was asked for last
+                self.next_frame = Instant::now();
+            }
+
+            let now = Instant::now();
+            if self.is_rendering() && now >= self.next_frame {
+                let result = self.render(now);
+                self.report("Failed to write to the strip", result);
+
+                self.next_frame += self.interval;
+                if self.next_frame < now {
+                    // We can't keep up, don't try to catch up
+                    debug!("Rendering a frame took longer than {:?}", self.interval);
+                    self.next_frame = now + self.interval;
+                }
+            } else if !self.is_rendering() && self.shown && self.refresh_interval.is_some_and(|x| now >= self.last_write + x) {
+                let result = self.driver.refresh();
+                self.report("Failed to write to the strip", result);
+                self.last_write = now;
+            }
+
+            if !matches!(self.state, State::Fading(_)) {
+                self.waiting.drain(..).for_each(|x| { let _ = x.send(()); });
+            }
+            if !self.pending && !self.flushing.is_empty() {
+                let error = self.error.take();
+                for tx in self.flushing.drain(..) {
+                    let result = match &error {
+                        Some(e) => Err(Error::Output(e.clone())),
+                        None => Ok(()),
+                    };
+                    let _ = tx.send(result);
+                }
+            }
+        }
+
+        debug!("All engine handles dropped, stopping");
+    }
+
+    fn handle_and_report(&mut self, command: Command) {
+        let result = self.handle(command);
+        self.report("Failed to apply change", result);
+    }
+
+    /// Keep an error around until it is picked up by [Engine::flush].
+    /// `context` tells writing to the strip apart from changes which could not be applied.
+    fn report(&mut self, context: &str, result: Result<()>) {
+        if let Err(e) = result {
+            warn!("{context}: {e}");
+            self.error = Some(e.to_string());
+        }
+    }
+
+    /// Whether frames have to be rendered at the frame rate. When idle,
+    /// a dithered frame still has to be written continuously to look right.
+    fn is_rendering(&self) -> bool {
+        !matches!(self.state, State::Idle) || self.driver.needs_refresh()
+    }
+
+    /// When the next frame has to be written, if at all
+    fn deadline(&self) -> Option<Instant> {
+        if self.is_rendering() {
+            Some(self.next_frame)
+        } else if self.shown {
+            self.refresh_interval.map(|x| self.last_write + x)
+        } else {
+            None
+        }
+    }
+
+    /// Render at the next frame, but never sooner than the frame rate allows
+    fn schedule(&mut self) {
+        self.next_frame = self.next_frame.max(Instant::now());
+    }
+
+    /// The frame and brightness the strip is heading towards
+    fn target(&self) -> (Frame, f32) {
+        match &self.state {
+            State::Fading(fade) => (fade.to.clone(), fade.to_brightness),
+            _ => (self.driver.frame().clone(), self.driver.brightness()),
+        }
+    }
+
+    /// Move towards a new target, starting from whatever is currently shown
+    fn fade_to(&mut self, to: Frame, to_brightness: f32, transition: Transition) -> Result<()> {
+        if to.len() != self.driver.length() as usize {
+            return Err(Error::FrameLength { expected: self.driver.length() as usize, actual: to.len() });
+        }
+
+        if !matches!(self.state, State::Fading(_)) {
+            self.schedule();
+        }
+        self.pending = true;
+
+        // Without a transition the fade is over with its first frame
+        self.state = State::Fading(Fade {
+            from: self.driver.frame().clone(),
+            to,
+            from_brightness: self.driver.brightness(),
+            to_brightness,
+            started: Instant::now(),
+            transition,
+        });
+        Ok(())
+    }
+
+    fn handle(&mut self, command: Command) -> Result<()> {
+        self.handle_with(command, self.transition)
+    }
+
+    /// Handle a command, fading changes in with `transition`
+    fn handle_with(&mut self, command: Command, transition: Transition) -> Result<()> {
+        match command {
+            Command::Rgb(rgb) => {
+                let (_, brightness) = self.target();
+                self.fade_to(Frame::filled(self.driver.length(), rgb), brightness, transition)
+            },
+            Command::Kelvin(kelvin) => {
+                let (_, brightness) = self.target();
+                let rgb = self.driver.kelvin_to_rgb(kelvin);
+                self.fade_to(Frame::filled(self.driver.length(), rgb), brightness, transition)
+            },
+            Command::ZoneRgb(zone, rgb) => {
+                let (base, brightness) = self.target();
+                let length = self.driver.zone(&zone)?.length;
+                let frame = self.driver.compose_zone(&base, &zone, &Frame::filled(length, rgb))?;
+                self.fade_to(frame, brightness, transition)
+            },
+            Command::Frame(frame) => {
+                let (_, brightness) = self.target();
+                self.fade_to(frame, brightness, transition)
+            },
+            Command::Brightness(brightness) => {
+                let brightness = brightness.clamp(0.0, 1.0);
+                match self.state {
+                    // Effects pick up the new brightness with their next frame
+                    State::Effect(_) => {
+                        self.driver.set_brightness(brightness);
+                        self.pending = true;
+                        Ok(())
+                    },
+                    // Fading in the empty frame would turn the strip off, the first change shows the brightness
+                    State::Idle if !self.shown => {
+                        self.driver.set_brightness(brightness);
+                        Ok(())
+                    },
+                    _ => {
+                        let (frame, _) = self.target();
+                        self.fade_to(frame, brightness, transition)
+                    }
+                }
+            },
+            Command::StartEffect(effect) => {
+                self.schedule();
+                self.pending = true;
+                self.state = State::Effect(Running {
+                    effect,
+                    started: Instant::now(),
+                });
+                Ok(())
+            },
+            Command::StopEffect => {
+                if let State::Effect(_) = self.state {
+                    self.state = State::Idle;
+                }
+                Ok(())
+            },
+            Command::SetTransition(transition) => {
+                self.transition = transition;
+                Ok(())
+            },
+            Command::WithTransition(transition, command) => self.handle_with(*command, transition),
+            Command::SetRefreshInterval(interval) => {
+                self.refresh_interval = interval;
+                Ok(())
+            },
+            Command::WaitForTransition(tx) => {
+                self.waiting.push(tx);
+                Ok(())
+            },
+            Command::Flush(tx) => {
+                self.flushing.push(tx);
+                Ok(())
+            },
+        }
+    }
+
+    fn render(&mut self, now: Instant) -> Result<()> {
+        self.pending = false;
+        self.shown = true;
+        self.last_write = now;
+        match &mut self.state {
+            State::Idle => self.driver.refresh(),
+            State::Effect(running) => {
+                let mut frame = self.driver.frame().clone();
+                running.effect.render(now - running.started, &mut frame);
+                self.driver.write_frame(&frame)
+            },
+            State::Fading(fade) => {
+                let progress = fade.transition.progress(now - fade.started);
+                if progress >= 1.0 {
+                    self.driver.set_brightness(fade.to_brightness);
+                    let result = self.driver.write_frame(&fade.to);
+                    self.state = State::Idle;
+                    return result;
+                }
+
+                let brightness = fade.from_brightness + (fade.to_brightness - fade.from_brightness) * progress;
+                let frame = Frame::from(fade.from.pixels().iter()
+                    .zip(fade.to.pixels())
+                    .map(|(from, &to)| from.lerp(to, progress))
+                    .collect::<Vec<_>>());
+
+                self.driver.set_brightness(brightness);
+                self.driver.write_frame(&frame)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{io, thread};
+    use std::time::Duration;
+    use crate::{Driver, Easing, EffectKind, EffectParams, Engine, Error, MemoryOutput, Output, Rgb, Transition};
+
+    #[test]
+    fn test_effect_renders_until_stopped() {
+        let output = MemoryOutput::new();
+        let engine = Engine::spawn(Driver::with_output(Box::new(output.clone()), 10), 100);
+
+        engine.start_effect(EffectKind::Rainbow, &EffectParams::default()).unwrap();
+        assert_eq!(Some(EffectKind::Rainbow), engine.effect());
+        thread::sleep(Duration::from_millis(100));
+        assert!(output.frames().len() > 2);
+
+        engine.set_rgb(Rgb::new(255, 0, 0)).unwrap();
+        assert_eq!(None, engine.effect());
+        thread::sleep(Duration::from_millis(50));
+
+        let written = output.frames().len();
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(written, output.frames().len());
+        assert_eq!(Rgb::new(255, 0, 0), output.last().unwrap()[0]);
+    }
+
+    #[test]
+    fn test_transition_fades() {
+        let output = MemoryOutput::new();
+        let engine = Engine::spawn(Driver::with_output(Box::new(output.clone()), 1), 100);
+        engine.set_transition(Transition { duration_ms: 100, easing: Easing::Linear }).unwrap();
+
+        engine.set_rgb(Rgb::new(200, 0, 0)).unwrap();
+        engine.wait_for_transition().unwrap();
+
+        let frames = output.frames();
+        assert!(frames.len() > 2);
+        // Every step moves towards the target
+        assert!(frames.windows(2).all(|x| x[0][0].r <= x[1][0].r));
+        assert_eq!(Rgb::new(200, 0, 0), frames.last().unwrap()[0]);
+    }
+
+    #[test]
+    fn test_with_transition() {
+        let output = MemoryOutput::new();
+        let engine = Engine::spawn(Driver::with_output(Box::new(output.clone()), 1), 100);
+        let fading = engine.with_transition(Transition { duration_ms: 100, easing: Easing::Linear });
+
+        fading.set_rgb(Rgb::new(200, 0, 0)).unwrap();
+        fading.wait_for_transition().unwrap();
+        let faded = output.frames().len();
+        assert!(faded > 2);
+
+        // The transition set on the engine is left alone
+        engine.set_rgb(Rgb::new(0, 200, 0)).unwrap();
+        engine.flush().unwrap();
+        assert_eq!(faded + 1, output.frames().len());
+        assert_eq!(Rgb::new(0, 200, 0), output.last().unwrap()[0]);
+    }
+
+    #[test]
+    fn test_brightness_before_first_change() {
+        let output = MemoryOutput::new();
+        let engine = Engine::spawn(Driver::with_output(Box::new(output.clone()), 1), 100);
+        engine.set_refresh_interval(Some(Duration::from_millis(10))).unwrap();
+
+        // Writing the empty frame would turn off what the strip showed before
+        engine.set_brightness(0.5).unwrap();
+        engine.flush().unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert!(output.frames().is_empty());
+
+        engine.set_rgb(Rgb::new(255, 0, 0)).unwrap();
+        engine.flush().unwrap();
+        let frame = output.last().unwrap();
+        assert!(frame[0].r > 0 && frame[0].r < 255);
+    }
+
+    #[test]
+    fn test_refreshes_dithered_frame() {
+        let output = MemoryOutput::new();
+        let mut driver = Driver::with_output(Box::new(output.clone()), 1);
+        driver.set_dithering(true);
+        driver.set_brightness(0.1);
+        let engine = Engine::spawn(driver, 100);
+
+        engine.set_rgb(Rgb::new(255, 0, 0)).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        let frames = output.frames();
+        assert!(frames.len() > 2);
+        assert!(frames.iter().any(|x| x[0] != frames[0][0]));
+    }
+
+    #[test]
+    fn test_coalesces_changes() {
+        let output = MemoryOutput::new();
+        let engine = Engine::spawn(Driver::with_output(Box::new(output.clone()), 1), 10);
+
+        for r in 0..=100 {
+            engine.set_rgb(Rgb::new(r, 0, 0)).unwrap();
+        }
+        engine.flush().unwrap();
+
+        // At 10 FPS there is no time to show every color in turn
+        let frames = output.frames();
+        assert!(frames.len() < 5);
+        assert_eq!(Rgb::new(100, 0, 0), frames.last().unwrap()[0]);
+    }
+
+    #[test]
+    fn test_skips_unchanged_frames() {
+        let output = MemoryOutput::new();
+        let engine = Engine::spawn(Driver::with_output(Box::new(output.clone()), 1), 100);
+
+        engine.set_rgb(Rgb::new(255, 0, 0)).unwrap();
+        engine.flush().unwrap();
+        engine.set_rgb(Rgb::new(255, 0, 0)).unwrap();
+        engine.flush().unwrap();
+        assert_eq!(1, output.frames().len());
+    }
+
+    #[test]
+    fn test_refresh_interval() {
+        let output = MemoryOutput::new();
+        let engine = Engine::spawn(Driver::with_output(Box::new(output.clone()), 1), 100);
+        engine.set_refresh_interval(Some(Duration::from_millis(20))).unwrap();
+
+        engine.set_rgb(Rgb::new(255, 0, 0)).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        assert!(output.frames().len() > 2);
+    }
+
+    #[test]
+    fn test_reports_errors() {
+        struct Unplugged;
+
+        impl Output for Unplugged {
+            fn write(&mut self, _: &[Rgb]) -> crate::Result<()> {
+                Err(io::Error::from(io::ErrorKind::BrokenPipe).into())
+            }
+        }
+
+        let engine = Engine::spawn(Driver::with_output(Box::new(Unplugged), 1), 100);
+        engine.set_rgb(Rgb::new(255, 0, 0)).unwrap();
+        assert!(matches!(engine.flush(), Err(Error::Output(_))));
+        // Every error is only reported once
+        assert!(engine.flush().is_ok());
+
+        engine.set_zone_rgb("desk", Rgb::new(255, 0, 0)).unwrap();
+        assert!(engine.flush().is_err());
+    }
+
+    #[test]
+    fn test_stopped_engine_runs_no_effect() {
+        struct Broken;
+
+        impl Output for Broken {
+            fn write(&mut self, _: &[Rgb]) -> crate::Result<()> {
+                panic!("Stopping the engine thread");
+            }
+        }
+
+        let engine = Engine::spawn(Driver::with_output(Box::new(Broken), 1), 100);
+        engine.set_rgb(Rgb::new(255, 0, 0)).unwrap();
+        assert!(matches!(engine.flush(), Err(Error::EngineStopped)));
+
+        assert!(matches!(engine.start_effect(EffectKind::Rainbow, &EffectParams::default()), Err(Error::EngineStopped)));
+        assert_eq!(None, engine.effect());
+    }
+}
@@ -4,10 +4,52 @@ pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug, Error)]
 pub enum Error {
-    #[error("Error in underlying driver: {0}")]
-    Ws28xx(String),
     #[error("No SPI device could be found")]
     NoSpiDev,
+    #[error("SPI clock speed of {0} Hz is not supported, it must be between 2 and 38 MHz")]
+    SpiSpeed(u32),
+    #[error("Frame has {actual} LEDs, but the strip has {expected}")]
+    FrameLength { expected: usize, actual: usize },
+    #[error("Invalid chipset '{0}', expected 'ws28xx', 'apa102', 'sk9822', 'ws2801' or 'lpd8806'")]
+    Chipset(String),
+    #[error("{0} strips have no white channel, only ws28xx strips can be RGBW")]
+    WhiteChannel(crate::Chipset),
+    #[error("Invalid network protocol '{0}', expected 'ddp' or 'wled'")]
+    NetworkProtocol(String),
+    #[error("Could not resolve the address '{0}'")]
+    Address(String),
+    #[error("Invalid color order '{0}'")]
+    ColorOrder(String),
+    #[error("Invalid white extraction '{0}', expected 'none', 'min' or a temperature like '4500K'")]
+    WhiteExtraction(String),
+    #[error("The white LED's temperature is set, but the strip has no white channel")]
+    WhiteLedKelvin,
+    #[error("Invalid white balance '{0}', expected three comma separated factors")]
+    WhiteBalance(String),
+    #[error("Invalid brightness curve '{0}'")]
+    BrightnessCurve(String),
+    #[error("Invalid color '{0}', expected a hex color like '#ff8800', a CSS color name or 'hsv(30, 100%, 100%)'")]
+    Color(String),
+    #[error("Invalid effect '{0}'")]
+    Effect(String),
+    #[error("Invalid easing '{0}'")]
+    Easing(String),
+    #[error("The driver thread has stopped")]
+    EngineStopped,
+    #[error("Writing to the strip failed: {0}")]
+    Output(String),
+    #[error("{0}")]
+    Zone(String),
+    #[error("No zone named '{0}'")]
+    UnknownZone(String),
+    #[error("{0}")]
+    Layout(String),
+    #[error("{0}")]
+    Strip(String),
+    #[error("No strip named '{0}'")]
+    UnknownStrip(String),
+    #[error("Invalid recording: {0}")]
+    Recording(String),
     #[error("IO: {0}")]
     Io(#[from] std::io::Error),
 }
\ No newline at end of file
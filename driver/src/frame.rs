@@ -0,0 +1,133 @@
+use std::ops::{Index, IndexMut};
+use crate::Rgb;
+
+/// A buffer holding one color per LED on the strip.
+/// Index `0` is the LED closest to the controller.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame(Vec<Rgb>);
+
+impl Frame {
+    /// Create a frame of `length` LEDs, all turned off
+    pub fn new(length: u16) -> Self {
+        Self::filled(length, Rgb::off())
+    }
+
+    /// Create a frame of `length` LEDs, all set to the same color
+    pub fn filled(length: u16, rgb: Rgb) -> Self {
+        Self(vec![rgb; length as usize])
+    }
+
+    /// Create a frame of `length` LEDs, blending evenly from one color to the next.
+    /// The first LED has the first color and the last LED the last color.
+    pub fn gradient(length: u16, colors: &[Rgb]) -> Self {
+        let (first, last) = match colors {
+            [] => return Self::new(length),
+            [color] => return Self::filled(length, *color),
+            [first, .., last] => (*first, *last),
+        };
+        if length < 2 {
+            return Self::filled(length, first);
+        }
+
+        let segments = colors.len() - 1;
+        Self((0..length as usize)
+            .map(|i| {
+                let position = i as f32 / (length - 1) as f32 * segments as f32;
+                match position as usize {
+                    x if x >= segments => last,
+                    x => colors[x].lerp(colors[x + 1], position - x as f32),
+                }
+            })
+            .collect())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Set every LED in the frame to the same color
+    pub fn fill(&mut self, rgb: Rgb) {
+        self.0.iter_mut().for_each(|x| *x = rgb);
+    }
+
+    pub fn get(&self, index: usize) -> Option<Rgb> {
+        self.0.get(index).copied()
+    }
+
+    /// Set the color of a single LED.
+    /// Returns `false` if the index is outside of the frame.
+    pub fn set(&mut self, index: usize, rgb: Rgb) -> bool {
+        match self.0.get_mut(index) {
+            Some(x) => {
+                *x = rgb;
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn pixels(&self) -> &[Rgb] {
+        &self.0
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [Rgb] {
+        &mut self.0
+    }
+}
+
+impl From<Vec<Rgb>> for Frame {
+    fn from(pixels: Vec<Rgb>) -> Self {
+        Self(pixels)
+    }
+}
+
+impl Index<usize> for Frame {
+    type Output = Rgb;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.0[index]
+    }
+}
+
+impl IndexMut<usize> for Frame {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.0[index]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Frame, Rgb};
+
+    #[test]
+    fn test_filled() {
+        let frame = Frame::filled(3, Rgb::new(1, 2, 3));
+        assert_eq!(3, frame.len());
+        assert!(frame.pixels().iter().all(|x| *x == Rgb::new(1, 2, 3)));
+    }
+
+    #[test]
+    fn test_gradient() {
+        let frame = Frame::gradient(5, &[Rgb::new(0, 0, 0), Rgb::new(200, 0, 0), Rgb::new(200, 0, 200)]);
+        assert_eq!(vec![
+            Rgb::new(0, 0, 0),
+            Rgb::new(100, 0, 0),
+            Rgb::new(200, 0, 0),
+            Rgb::new(200, 0, 100),
+            Rgb::new(200, 0, 200),
+        ], frame.pixels());
+        assert_eq!(Frame::filled(1, Rgb::new(1, 2, 3)), Frame::gradient(1, &[Rgb::new(1, 2, 3), Rgb::off()]));
+    }
+
+    #[test]
+    fn test_set_out_of_bounds() {
+        let mut frame = Frame::new(2);
+        assert!(frame.set(1, Rgb::new(255, 0, 0)));
+        assert!(!frame.set(2, Rgb::new(255, 0, 0)));
+        assert_eq!(Some(Rgb::new(255, 0, 0)), frame.get(1));
+    }
+}
@@ -0,0 +1,129 @@
+use serde::{Serialize, Deserialize};
+use crate::error::{Error, Result};
+use crate::{Rgb, WhiteExtraction};
+
+/// Converts color temperatures to colors for a particular strip.
+/// Without calibration, temperatures are approximated with [Rgb::from_kelvin].
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct KelvinCalibration {
+    // Plain values have to come before tables, or the config can't be serialized
+    /// For RGBW strips, the temperature of the white LED, e.g. `4500`.
+    /// Color temperatures are then made with the white LED as far as possible, which looks more natural.
+    pub white_led_kelvin: Option<u16>,
+    /// Colors measured to look like a temperature on this strip, e.g. by comparing it to a bulb.
+    /// Temperatures in between are interpolated, beyond them the nearest point is followed.
+    #[serde(default)]
+    pub points: Vec<KelvinPoint>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KelvinPoint {
+    pub kelvin: u16,
+    pub color: Rgb,
+}
+
+impl KelvinCalibration {
+    /// The color which looks like `kelvin` on this strip
+    pub fn to_rgb(&self, kelvin: u16) -> Rgb {
+        let rgb = self.calibrate(kelvin);
+        match self.white_led_kelvin {
+            Some(white) => WhiteExtraction::Temperature { kelvin: white }.apply(rgb),
+            None => rgb,
+        }
+    }
+
+    /// Check the calibration fits a strip. Without a white channel, [KelvinCalibration::white_led_kelvin]
+    /// would move most of a temperature into a channel which is never written.
+    pub fn validate(&self, white_channel: bool) -> Result<()> {
+        match self.white_led_kelvin {
+            Some(_) if !white_channel => Err(Error::WhiteLedKelvin),
+            _ => Ok(()),
+        }
+    }
+
+    /// Every point is stored as its difference with the uncalibrated color,
+    /// which is interpolated and applied to the uncalibrated color of `kelvin`
+    fn calibrate(&self, kelvin: u16) -> Rgb {
+        let offset = |point: &KelvinPoint| {
+            let reference = Rgb::from_kelvin(point.kelvin);
+            [
+                point.color.r as f32 - reference.r as f32,
+                point.color.g as f32 - reference.g as f32,
+                point.color.b as f32 - reference.b as f32,
+            ]
+        };
+
+        let below = self.points.iter().filter(|x| x.kelvin <= kelvin).max_by_key(|x| x.kelvin);
+        let above = self.points.iter().filter(|x| x.kelvin >= kelvin).min_by_key(|x| x.kelvin);
+        let offset = match (below, above) {
+            (None, None) => return Rgb::from_kelvin(kelvin),
+            (Some(x), None) | (None, Some(x)) => offset(x),
+            (Some(below), Some(above)) if below.kelvin == above.kelvin => offset(below),
+            (Some(below), Some(above)) => {
+                let t = (kelvin - below.kelvin) as f32 / (above.kelvin - below.kelvin) as f32;
+                let (below, above) = (offset(below), offset(above));
+                [0, 1, 2].map(|i| below[i] + (above[i] - below[i]) * t)
+            }
+        };
+
+        let rgb = Rgb::from_kelvin(kelvin);
+        let channel = |value: u8, offset: f32| (value as f32 + offset).round().clamp(0.0, 255.0) as u8;
+        Rgb::new(channel(rgb.r, offset[0]), channel(rgb.g, offset[1]), channel(rgb.b, offset[2]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{KelvinCalibration, KelvinPoint, Rgb};
+
+    #[test]
+    fn test_uncalibrated() {
+        assert_eq!(Rgb::from_kelvin(2700), KelvinCalibration::default().to_rgb(2700));
+    }
+
+    #[test]
+    fn test_points() {
+        let calibration = KelvinCalibration {
+            points: vec![
+                KelvinPoint { kelvin: 2700, color: Rgb::new(255, 150, 60) },
+                KelvinPoint { kelvin: 6500, color: Rgb::new(240, 255, 255) },
+            ],
+            white_led_kelvin: None,
+        };
+
+        assert_eq!(Rgb::new(255, 150, 60), calibration.to_rgb(2700));
+        assert_eq!(Rgb::new(240, 255, 255), calibration.to_rgb(6500));
+
+        // In between, the red channel drifts from the uncalibrated 255 to 240
+        let between = calibration.to_rgb(4600);
+        assert!(between.r < 255 && between.r > 240);
+    }
+
+    #[test]
+    fn test_white_led() {
+        let calibration = KelvinCalibration {
+            points: Vec::new(),
+            white_led_kelvin: Some(4500),
+        };
+
+        assert_eq!(Rgb::new_rgbw(0, 0, 0, 255), calibration.to_rgb(4500));
+        // Warmer than the white LED, so red has to make up for it
+        let warm = calibration.to_rgb(2700);
+        assert!(warm.w > 0 && warm.r > warm.b);
+        assert!(calibration.validate(true).is_ok());
+    }
+
+    #[test]
+    fn test_white_led_without_white_channel() {
+        let calibration = KelvinCalibration {
+            points: Vec::new(),
+            white_led_kelvin: Some(4500),
+        };
+
+        // Nearly everything would end up in the white channel, which RGB strips drop
+        let rgb = calibration.to_rgb(4500);
+        assert_eq!(Rgb::new(0, 0, 0), Rgb::new(rgb.r, rgb.g, rgb.b));
+        assert!(calibration.validate(false).is_err());
+        assert!(KelvinCalibration::default().validate(false).is_ok());
+    }
+}
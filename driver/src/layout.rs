@@ -0,0 +1,133 @@
+use serde::{Serialize, Deserialize};
+use crate::error::{Error, Result};
+
+/// How the pixels of a frame are laid out on the physical strip.
+/// Frames are written in logical order, e.g. from left to right across a desk,
+/// regardless of where the strip starts, which way it runs and which LEDs are skipped.
+/// The default maps pixel `0` to the LED closest to the controller.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Layout {
+    /// The LED showing pixel `0`, counting from the controller.
+    /// Pixels past the end of the strip wrap around to its start.
+    #[serde(default)]
+    pub offset: u16,
+    /// Whether pixels run towards the controller rather than away from it
+    #[serde(default)]
+    pub reversed: bool,
+    /// LEDs which never show a pixel and are kept off, e.g. because they are broken
+    /// or hidden behind a corner. Frames are shorter than the strip by this many LEDs.
+    #[serde(default)]
+    pub skip: Vec<u16>,
+    /// Set if the strip is folded into a matrix
+    pub matrix: Option<Matrix>,
+}
+
+/// A strip folded into rows of equal length. Frames hold the rows one after another,
+/// every row running in the same direction, see [Layout::index].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Matrix {
+    /// The number of LEDs in a row
+    pub width: u16,
+    /// Whether every other row runs in the opposite direction,
+    /// as when a strip is folded back and forth
+    #[serde(default)]
+    pub serpentine: bool,
+}
+
+impl Layout {
+    /// The index in a frame of the pixel at column `x` and row `y`, counted from the first pixel.
+    /// Strips which are not folded into a matrix are a single row.
+    pub fn index(&self, x: u16, y: u16) -> Option<usize> {
+        match self.matrix {
+            Some(matrix) if x < matrix.width => Some(y as usize * matrix.width as usize + x as usize),
+            Some(_) => None,
+            None => (y == 0).then_some(x as usize),
+        }
+    }
+
+    /// The LED on a strip of `length` LEDs showing each pixel of a frame
+    pub(crate) fn map(&self, length: u16) -> Result<Vec<usize>> {
+        if let Some(led) = self.skip.iter().find(|&&x| x >= length) {
+            return Err(Error::Layout(format!("Skipped LED {led} is beyond the end of the strip")));
+        }
+        if length > 0 && self.offset >= length {
+            return Err(Error::Layout(format!("Offset {} is beyond the end of the strip", self.offset)));
+        }
+
+        let leds = (0..length)
+            .filter(|x| !self.skip.contains(x))
+            .map(usize::from)
+            .collect::<Vec<_>>();
+        let count = leds.len();
+
+        // If the LED at the offset is skipped, pixel 0 is on the next one
+        let start = leds.iter().position(|&x| x >= self.offset as usize).unwrap_or(0);
+        let led = |position: usize| if self.reversed {
+            leds[(start + count - position) % count]
+        } else {
+            leds[(start + position) % count]
+        };
+
+        let matrix = match self.matrix {
+            Some(x) => x,
+            None => return Ok((0..count).map(led).collect()),
+        };
+
+        let width = matrix.width as usize;
+        if width == 0 || count % width != 0 {
+            return Err(Error::Layout(format!("The {count} LEDs of the strip can't be divided into rows of {width}")));
+        }
+
+        Ok((0..count)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                let x = if matrix.serpentine && y % 2 == 1 {
+                    width - 1 - x
+                } else {
+                    x
+                };
+
+                led(y * width + x)
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Layout, Matrix};
+
+    #[test]
+    fn test_default_is_identity() {
+        assert_eq!(vec![0, 1, 2, 3], Layout::default().map(4).unwrap());
+    }
+
+    #[test]
+    fn test_offset_reversed_skip() {
+        let layout = Layout { offset: 2, reversed: true, skip: vec![4], matrix: None };
+        assert_eq!(vec![2, 1, 0, 5, 3], layout.map(6).unwrap());
+
+        // Starts at the next LED if the one at the offset is skipped
+        let layout = Layout { offset: 2, reversed: false, skip: vec![2], matrix: None };
+        assert_eq!(vec![3, 0, 1], layout.map(4).unwrap());
+    }
+
+    #[test]
+    fn test_serpentine() {
+        let layout = Layout {
+            matrix: Some(Matrix { width: 3, serpentine: true }),
+            ..Layout::default()
+        };
+
+        assert_eq!(vec![0, 1, 2, 5, 4, 3], layout.map(6).unwrap());
+        assert_eq!(Some(4), layout.index(1, 1));
+        assert_eq!(None, layout.index(3, 0));
+        assert!(layout.map(7).is_err());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Layout { skip: vec![4], ..Layout::default() }.map(4).is_err());
+        assert!(Layout { offset: 4, ..Layout::default() }.map(4).is_err());
+    }
+}
@@ -1,61 +1,438 @@
 use std::fmt;
-use tracing::{info, trace};
-use ws2818_rgb_led_spi_driver::adapter_gen::WS28xxAdapter;
-use ws2818_rgb_led_spi_driver::adapter_spi::WS28xxSpiAdapter;
-use ws2818_rgb_led_spi_driver::encoding::encode_rgb;
-use error::Result;
+use tracing::{debug, trace};
+use crate::correction::CorrectionTable;
+use crate::dither::Dither;
 
+mod chipset;
+mod color;
+mod correction;
+mod css;
+mod dither;
+mod effect;
+mod encoding;
+mod engine;
 mod error;
+mod frame;
+mod kelvin;
+mod layout;
+mod output;
+mod power;
+mod recording;
 mod spidev;
+mod strips;
+mod transition;
+mod zone;
 
+pub use chipset::*;
+pub use color::*;
+pub use correction::*;
+pub use effect::*;
+pub use encoding::*;
+pub use engine::*;
 pub use error::*;
+pub use frame::*;
+pub use kelvin::*;
+pub use layout::*;
+pub use output::*;
+pub use power::*;
+pub use recording::*;
 pub use spidev::*;
-
-pub struct Rgb((u8, u8, u8));
-
-impl Rgb {
-    pub fn new(red: u8, green: u8, blue: u8) -> Self {
-        Self((red, green, blue))
-    }
-
-    pub fn off() -> Self {
-        Self((0, 0, 0))
-    }
-}
+pub use strips::*;
+pub use transition::*;
+pub use zone::*;
 
 pub struct Driver {
-    adapter: WS28xxSpiAdapter,
-    length: u16
+    output: Box<dyn Output>,
+    /// The number of LEDs on the strip
+    strip_length: u16,
+    /// The number of pixels in a frame
+    length: u16,
+    layout: Layout,
+    /// The LED showing each pixel of the frame
+    leds: Vec<usize>,
+    correction: Correction,
+    brightness: f32,
+    table: CorrectionTable,
+    power_budget: PowerBudget,
+    power: PowerEstimate,
+    kelvin: KelvinCalibration,
+    zones: Vec<Zone>,
+    frame: Frame,
+    dither: Option<Dither>,
+    /// Whether the last frame written needs more frames to be dithered
+    dithering: bool,
+    /// Whether the output shows the current frame with the current settings
+    written: bool,
+    /// Reused for every frame, so writing a frame does not allocate
+    corrected: Vec<Rgb16>,
+    pixels: Vec<Rgb>,
 }
 
 impl fmt::Debug for Driver {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Driver {{ length: {} }}", self.length)
+        write!(f, "Driver {{ length: {}, correction: {:?}, brightness: {} }}", self.length, self.correction, self.brightness)
     }
 }
 
 impl Driver {
-    pub fn new(spidev: &Spidev, length: u16) -> Result<Self> {
-        trace!("Creating WS28xxSpiAdapter");
-        let adapter = WS28xxSpiAdapter::new(&spidev.0).map_err(|e| Error::Ws28xx(e))?;
-        Ok(Self {
-            adapter,
-            length
-        })
+    /// Create a driver for a strip connected to the provided SPI device
+    pub fn new(spidev: &Spidev, length: u16, format: PixelFormat) -> Result<Self> {
+        let output = SpiOutput::new(spidev, format)?;
+        Ok(Self::with_output(Box::new(output), length))
     }
 
-    pub fn set_rgb(&mut self, rgb: Rgb) -> Result<()> {
-        trace!("Encoding RGB");
-        let mut rgb_bits = Vec::with_capacity(self.length as usize * 48);
-        let (r, g, b) = rgb.0;
-        trace!("Setting R{r} G{g} B{b}");
-        for _ in 0..self.length {
-            // For some reason green and blue need to be swapped
-            rgb_bits.extend_from_slice(&encode_rgb(r, b, g));
+    /// Create a driver writing to any [Output]
+    pub fn with_output(output: Box<dyn Output>, length: u16) -> Self {
+        let correction = Correction::default();
+        Self {
+            output,
+            strip_length: length,
+            length,
+            layout: Layout::default(),
+            leds: (0..length as usize).collect(),
+            table: CorrectionTable::new(&correction, 1.0),
+            correction,
+            brightness: 1.0,
+            power_budget: PowerBudget::default(),
+            power: PowerEstimate::default(),
+            kelvin: KelvinCalibration::default(),
+            zones: vec![Zone::whole(length)],
+            frame: Frame::new(length),
+            dither: None,
+            dithering: false,
+            written: false,
+            corrected: Vec::with_capacity(length as usize),
+            pixels: Vec::with_capacity(length as usize),
+        }
+    }
+
+    /// The number of pixels in a frame. This is the length of the strip,
+    /// minus the LEDs skipped by the [Layout].
+    pub fn length(&self) -> u16 {
+        self.length
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// Set where the pixels of frames are shown on the strip.
+    /// This changes the length of frames, so the current frame is cleared and zones are reset.
+    pub fn set_layout(&mut self, layout: Layout) -> Result<()> {
+        self.leds = layout.map(self.strip_length)?;
+        self.length = self.leds.len() as u16;
+        self.layout = layout;
+        self.frame = Frame::new(self.length);
+        self.zones = vec![Zone::whole(self.length)];
+        self.written = false;
+        Ok(())
+    }
+
+    pub fn correction(&self) -> &Correction {
+        &self.correction
+    }
+
+    /// Set the color correction applied to every frame.
+    /// Takes effect on the next write.
+    pub fn set_correction(&mut self, correction: Correction) {
+        self.table = CorrectionTable::new(&correction, self.brightness);
+        self.correction = correction;
+        self.written = false;
+    }
+
+    pub fn brightness(&self) -> f32 {
+        self.brightness
+    }
+
+    /// Set the brightness level of the strip, in the range `0.0..=1.0`.
+    /// The level is mapped to LED output using the configured [BrightnessCurve].
+    /// Takes effect on the next write.
+    pub fn set_brightness(&mut self, brightness: f32) {
+        let brightness = brightness.clamp(0.0, 1.0);
+        if brightness != self.brightness {
+            self.brightness = brightness;
+            self.table = CorrectionTable::new(&self.correction, self.brightness);
+            self.written = false;
+        }
+    }
+
+    pub fn power_budget(&self) -> &PowerBudget {
+        &self.power_budget
+    }
+
+    /// Set the current model and limit used to scale down frames.
+    /// Takes effect on the next write.
+    pub fn set_power_budget(&mut self, power_budget: PowerBudget) {
+        self.power_budget = power_budget;
+        self.written = false;
+    }
+
+    pub fn kelvin_calibration(&self) -> &KelvinCalibration {
+        &self.kelvin
+    }
+
+    /// Set how color temperatures are converted to colors for this strip
+    pub fn set_kelvin_calibration(&mut self, calibration: KelvinCalibration) {
+        self.kelvin = calibration;
+    }
+
+    /// The color which looks like the color temperature `kelvin` on this strip
+    pub fn kelvin_to_rgb(&self, kelvin: u16) -> Rgb {
+        self.kelvin.to_rgb(kelvin)
+    }
+
+    /// The color shown on this strip for `color`
+    pub fn color_to_rgb(&self, color: Color) -> Rgb {
+        match color {
+            Color::Rgb(rgb) => rgb,
+            Color::Kelvin(kelvin) => self.kelvin_to_rgb(kelvin),
         }
+    }
+
+    /// The estimated current draw of the last frame written
+    pub fn power(&self) -> PowerEstimate {
+        self.power
+    }
+
+    /// Enable or disable temporal dithering.
+    /// Colors are corrected at 16 bits per channel, and with dithering enabled
+    /// every LED alternates between the two nearest 8 bit values so colors too dim
+    /// for 8 bits are shown accurately. This requires frames to be written continuously,
+    /// see [Driver::needs_refresh].
+    pub fn set_dithering(&mut self, enabled: bool) {
+        self.dither = enabled.then(|| Dither::new(self.strip_length));
+        self.dithering = false;
+        self.written = false;
+    }
 
-        info!("Writing RGB");
-        self.adapter.write_encoded_rgb(&rgb_bits).map_err(|e| Error::Ws28xx(e))?;
+    pub fn is_dithering_enabled(&self) -> bool {
+        self.dither.is_some()
+    }
+
+    /// Whether the current frame has to keep being written with [Driver::refresh]
+    /// for dithering to show it accurately
+    pub fn needs_refresh(&self) -> bool {
+        self.dithering
+    }
+
+    /// Write the current frame again, even if the output already shows it
+    pub fn refresh(&mut self) -> Result<()> {
+        self.render()
+    }
+
+    pub fn zones(&self) -> &[Zone] {
+        &self.zones
+    }
+
+    /// Divide the strip into zones which can be colored independently.
+    /// An empty list resets to a single zone covering the whole strip.
+    pub fn set_zones(&mut self, zones: Vec<Zone>) -> Result<()> {
+        if zones.is_empty() {
+            self.zones = vec![Zone::whole(self.length)];
+            return Ok(());
+        }
+
+        Zone::validate(&zones, self.length)?;
+        self.zones = zones;
         Ok(())
     }
-}
\ No newline at end of file
+
+    pub fn zone(&self, name: &str) -> Result<&Zone> {
+        self.zones.iter()
+            .find(|x| x.name == name)
+            .ok_or_else(|| Error::UnknownZone(name.to_string()))
+    }
+
+    /// The frame currently shown on the strip, before correction
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    /// Set every LED in a zone to the same color, leaving the rest of the strip as-is
+    pub fn set_zone_rgb(&mut self, zone: &str, rgb: Rgb) -> Result<()> {
+        let length = self.zone(zone)?.length;
+        self.write_zone_frame(zone, &Frame::filled(length, rgb))
+    }
+
+    /// Write a color for every LED in a zone, leaving the rest of the strip as-is.
+    /// The frame must be exactly as long as the zone.
+    pub fn write_zone_frame(&mut self, zone: &str, frame: &Frame) -> Result<()> {
+        let strip = self.compose_zone(&self.frame, zone, frame)?;
+        self.write_frame(&strip)
+    }
+
+    /// Place the frame for a zone on top of a frame for the whole strip.
+    /// The zone's frame must be exactly as long as the zone.
+    pub fn compose_zone(&self, base: &Frame, zone: &str, frame: &Frame) -> Result<Frame> {
+        let zone = self.zone(zone)?;
+        if frame.len() != zone.length as usize {
+            return Err(Error::FrameLength { expected: zone.length as usize, actual: frame.len() });
+        }
+
+        let mut strip = base.clone();
+        for (i, &rgb) in frame.pixels().iter().enumerate() {
+            strip[zone.strip_index(i as u16)] = rgb;
+        }
+
+        Ok(strip)
+    }
+
+    /// Set every LED on the strip to the same color
+    pub fn set_rgb(&mut self, rgb: Rgb) -> Result<()> {
+        let Rgb { r, g, b, w } = rgb;
+        trace!("Setting R{r} G{g} B{b} W{w}");
+        if self.is_shown(|frame| frame.pixels().iter().all(|&x| x == rgb)) {
+            return Ok(());
+        }
+
+        self.frame.fill(rgb);
+        self.render()
+    }
+
+    /// Write a color for every individual LED to the strip.
+    /// The frame must be exactly as long as the strip.
+    /// Nothing is written if the strip already shows the frame.
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+        if frame.len() != self.length as usize {
+            return Err(Error::FrameLength { expected: self.length as usize, actual: frame.len() });
+        }
+        if self.is_shown(|x| x == frame) {
+            return Ok(());
+        }
+
+        self.frame.pixels_mut().copy_from_slice(frame.pixels());
+        self.render()
+    }
+
+    /// Whether the output already shows a frame for which `matches` returns `true`,
+    /// so writing it again can be skipped. Dithered frames always have to be written.
+    fn is_shown<F: FnOnce(&Frame) -> bool>(&self, matches: F) -> bool {
+        self.written && !self.dithering && matches(&self.frame)
+    }
+
+    /// Write the current frame to the output
+    fn render(&mut self) -> Result<()> {
+        // LEDs without a pixel stay off
+        self.corrected.clear();
+        self.corrected.resize(self.strip_length as usize, Rgb16::default());
+        for (&pixel, &led) in self.frame.pixels().iter().zip(&self.leds) {
+            self.corrected[led] = self.table.apply(pixel);
+        }
+
+        self.power = self.power_budget.limit(&mut self.corrected);
+        if self.power.is_limited() {
+            debug!("Frame would draw {:.0} mA, limited to {:.0} mA", self.power.requested_milliamps, self.power.milliamps);
+        } else {
+            debug!("Frame draws an estimated {:.0} mA", self.power.milliamps);
+        }
+
+        trace!("Writing frame");
+        let result = match &mut self.dither {
+            // The output shows the extra precision itself
+            _ if self.output.is_wide() => {
+                self.dithering = false;
+                self.output.write_wide(&self.corrected)
+            },
+            Some(dither) => {
+                self.dithering = !Dither::is_exact(&self.corrected);
+                dither.apply(&self.corrected, &mut self.pixels);
+                self.output.write(&self.pixels)
+            },
+            None => {
+                self.pixels.clear();
+                self.pixels.extend(self.corrected.iter().map(Rgb16::to_rgb));
+                self.output.write(&self.pixels)
+            },
+        };
+
+        self.written = result.is_ok();
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{BrightnessCurve, Correction, Driver, Error, Frame, Layout, MemoryOutput, Rgb, Zone};
+
+    #[test]
+    fn test_set_rgb_fills_strip() {
+        let output = MemoryOutput::new();
+        let mut driver = Driver::with_output(Box::new(output.clone()), 4);
+        driver.set_rgb(Rgb::new(10, 20, 30)).unwrap();
+
+        assert_eq!(Some(Frame::filled(4, Rgb::new(10, 20, 30))), output.last());
+    }
+
+    #[test]
+    fn test_write_frame_wrong_length() {
+        let mut driver = Driver::with_output(Box::new(MemoryOutput::new()), 4);
+        let result = driver.write_frame(&Frame::new(3));
+        assert!(matches!(result, Err(Error::FrameLength { expected: 4, actual: 3 })));
+    }
+
+    #[test]
+    fn test_layout() {
+        let output = MemoryOutput::new();
+        let mut driver = Driver::with_output(Box::new(output.clone()), 4);
+        driver.set_layout(Layout { offset: 1, reversed: true, skip: vec![2], matrix: None }).unwrap();
+        assert_eq!(3, driver.length());
+
+        let (red, green, blue) = (Rgb::new(255, 0, 0), Rgb::new(0, 255, 0), Rgb::new(0, 0, 255));
+        driver.write_frame(&Frame::from(vec![red, green, blue])).unwrap();
+        assert_eq!(vec![green, red, Rgb::off(), blue], output.last().unwrap().pixels());
+    }
+
+    #[test]
+    fn test_zones_compose() {
+        let output = MemoryOutput::new();
+        let mut driver = Driver::with_output(Box::new(output.clone()), 5);
+        driver.set_zones(vec![
+            Zone { name: "left".to_string(), start: 0, length: 2, reversed: false },
+            Zone { name: "right".to_string(), start: 2, length: 3, reversed: true },
+        ]).unwrap();
+
+        let red = Rgb::new(255, 0, 0);
+        let blue = Rgb::new(0, 0, 255);
+        driver.set_zone_rgb("left", red).unwrap();
+        driver.write_zone_frame("right", &Frame::from(vec![blue, Rgb::off(), Rgb::off()])).unwrap();
+
+        assert_eq!(Some(Frame::from(vec![red, red, Rgb::off(), Rgb::off(), blue])), output.last());
+        assert!(matches!(driver.set_zone_rgb("middle", red), Err(Error::UnknownZone(_))));
+    }
+
+    #[test]
+    fn test_dithering_dim_color() {
+        let output = MemoryOutput::new();
+        let mut driver = Driver::with_output(Box::new(output.clone()), 1);
+        driver.set_correction(Correction { brightness_curve: BrightnessCurve::Linear, ..Correction::default() });
+        driver.set_brightness(0.01);
+
+        // Too dim for 8 bits, rounding shifts the hue towards red
+        driver.set_rgb(Rgb::new(255, 120, 0)).unwrap();
+        assert!(!driver.needs_refresh());
+        assert_eq!(Rgb::new(3, 1, 0), output.last().unwrap()[0]);
+
+        driver.set_dithering(true);
+        output.clear();
+        for _ in 0..100 {
+            driver.refresh().unwrap();
+        }
+        assert!(driver.needs_refresh());
+
+        let frames = output.frames();
+        let average = |channel: fn(&Rgb) -> u8| frames.iter().map(|x| channel(&x[0]) as f32).sum::<f32>() / frames.len() as f32;
+        assert!((average(|x| x.r) - 2.55).abs() < 0.05);
+        assert!((average(|x| x.g) - 1.2).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_brightness_is_applied() {
+        let output = MemoryOutput::new();
+        let mut driver = Driver::with_output(Box::new(output.clone()), 1);
+        driver.set_correction(Correction { brightness_curve: BrightnessCurve::Linear, ..Correction::default() });
+        driver.set_brightness(0.5);
+        driver.set_rgb(Rgb::new(255, 100, 0)).unwrap();
+
+        assert_eq!(Some(Frame::filled(1, Rgb::new(128, 50, 0))), output.last());
+    }
+}
@@ -0,0 +1,59 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use crate::chipset::Encoder;
+use crate::encoding::PixelFormat;
+use crate::error::Result;
+use crate::output::Output;
+use crate::{Rgb, Rgb16, Spidev};
+
+/// Writes the encoded bit stream, exactly as it would be sent
+/// over SPI at the default clock speed, to any writer. E.g. a file, a pipe or stdout.
+#[derive(Debug)]
+pub struct FileOutput<W: Write + Send> {
+    writer: W,
+    encoder: Encoder,
+}
+
+impl FileOutput<File> {
+    /// Open the file at `path` for writing, creating it if it does not exist
+    pub fn open<P: AsRef<Path>>(path: P, format: PixelFormat) -> Result<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        Self::new(file, format)
+    }
+}
+
+impl<W: Write + Send> FileOutput<W> {
+    pub fn new(writer: W, format: PixelFormat) -> Result<Self> {
+        Ok(Self {
+            writer,
+            encoder: Encoder::new(format, Spidev::DEFAULT_SPEED_HZ)?,
+        })
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + Send> Output for FileOutput<W> {
+    fn write(&mut self, pixels: &[Rgb]) -> Result<()> {
+        self.writer.write_all(self.encoder.encode(pixels))?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn is_wide(&self) -> bool {
+        self.encoder.is_wide()
+    }
+
+    fn write_wide(&mut self, pixels: &[Rgb16]) -> Result<()> {
+        self.writer.write_all(self.encoder.encode_wide(pixels))?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
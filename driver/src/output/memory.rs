@@ -0,0 +1,39 @@
+use std::sync::{Arc, Mutex};
+use crate::error::Result;
+use crate::output::Output;
+use crate::{Frame, Rgb};
+
+/// Records every frame written to it in memory.
+/// Clones share the same recording, so a clone can be kept around
+/// to inspect what was written after the output has been given to a [crate::Driver].
+#[derive(Debug, Clone, Default)]
+pub struct MemoryOutput {
+    frames: Arc<Mutex<Vec<Frame>>>,
+}
+
+impl MemoryOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All frames written so far, oldest first
+    pub fn frames(&self) -> Vec<Frame> {
+        self.frames.lock().unwrap().clone()
+    }
+
+    /// The most recently written frame
+    pub fn last(&self) -> Option<Frame> {
+        self.frames.lock().unwrap().last().cloned()
+    }
+
+    pub fn clear(&self) {
+        self.frames.lock().unwrap().clear();
+    }
+}
+
+impl Output for MemoryOutput {
+    fn write(&mut self, pixels: &[Rgb]) -> Result<()> {
+        self.frames.lock().unwrap().push(Frame::from(pixels.to_vec()));
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use crate::error::Result;
+use crate::{Rgb, Rgb16};
+
+mod file;
+mod memory;
+mod network;
+mod recorder;
+mod spi;
+mod terminal;
+
+pub use file::*;
+pub use memory::*;
+pub use network::*;
+pub use recorder::*;
+pub use spi::*;
+pub use terminal::*;
+
+/// Something a [crate::Driver] can write frames to,
+/// e.g. a physical LED strip.
+pub trait Output: Send {
+    /// Write one color per LED to the output
+    fn write(&mut self, pixels: &[Rgb]) -> Result<()>;
+
+    /// Whether the output can show more than 8 bits per channel.
+    /// If so, the driver writes frames with [Output::write_wide] instead of dithering them.
+    fn is_wide(&self) -> bool {
+        false
+    }
+
+    /// Write one 16 bit color per LED to the output.
+    /// By default the colors are rounded to 8 bits and written with [Output::write].
+    fn write_wide(&mut self, pixels: &[Rgb16]) -> Result<()> {
+        let pixels = pixels.iter().map(Rgb16::to_rgb).collect::<Vec<_>>();
+        self.write(&pixels)
+    }
+}
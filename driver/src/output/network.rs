@@ -0,0 +1,268 @@
+use std::fmt;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use tracing::trace;
+use crate::error::{Error, Result};
+use crate::output::Output;
+use crate::{Rgb, WhiteExtraction};
+
+/// How frames are sent to a controller on the network
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkProtocol {
+    /// The Distributed Display Protocol, supported by WLED, xLights and others
+    Ddp,
+    /// WLED's realtime UDP protocol. Frames which fit in a single packet are sent as DRGB,
+    /// or DRGBW for RGBW strips, longer frames as DNRGB.
+    /// WLED keeps showing the frames until it restarts.
+    Wled,
+}
+
+impl NetworkProtocol {
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Self::Ddp => 4048,
+            Self::Wled => 21324,
+        }
+    }
+}
+
+impl FromStr for NetworkProtocol {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "ddp" => Self::Ddp,
+            "wled" => Self::Wled,
+            _ => return Err(Error::NetworkProtocol(s.to_string()))
+        })
+    }
+}
+
+impl fmt::Display for NetworkProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Ddp => "ddp",
+            Self::Wled => "wled",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// DDP header flags: version 1, with the push flag set on the last packet of a frame
+const DDP_VERSION: u8 = 0x40;
+const DDP_PUSH: u8 = 0x01;
+/// DDP data types for 8 bits per channel
+const DDP_RGB: u8 = 0x0B;
+const DDP_RGBW: u8 = 0x1B;
+/// The default output of the receiving device
+const DDP_DESTINATION: u8 = 0x01;
+/// The most data WLED accepts in a single DDP packet, a multiple of both 3 and 4
+const DDP_MAX_DATA: usize = 1440;
+
+/// WLED realtime protocol types
+const WLED_DRGB: u8 = 2;
+const WLED_DRGBW: u8 = 3;
+const WLED_DNRGB: u8 = 4;
+/// Never return to WLED's own effects
+const WLED_TIMEOUT: u8 = 255;
+/// The most LEDs WLED accepts in a single packet of each type
+const WLED_DRGB_MAX: usize = 490;
+const WLED_DRGBW_MAX: usize = 367;
+const WLED_DNRGB_MAX: usize = 489;
+
+/// Sends frames over UDP to a controller on the network, e.g. an ESP32 running WLED.
+/// The controller takes care of the chipset and color order of its strip.
+#[derive(Debug)]
+pub struct NetworkOutput {
+    socket: UdpSocket,
+    protocol: NetworkProtocol,
+    /// Set for RGBW strips
+    white: Option<WhiteExtraction>,
+    /// DDP sequence number of the last frame, cycling through 1 to 15
+    sequence: u8,
+    /// Reused for every packet
+    packet: Vec<u8>,
+}
+
+impl NetworkOutput {
+    /// Send to `address`, a host name or IP address with an optional port.
+    /// The protocol's default port is used if no port is given.
+    pub fn new(address: &str, protocol: NetworkProtocol, white: Option<WhiteExtraction>) -> Result<Self> {
+        let target = address.to_socket_addrs()
+            .or_else(|_| (address, protocol.default_port()).to_socket_addrs())
+            .ok()
+            .and_then(|mut x| x.next())
+            .ok_or_else(|| Error::Address(address.to_string()))?;
+
+        trace!("Sending {protocol} to {target}");
+        let local = if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(target)?;
+
+        Ok(Self {
+            socket,
+            protocol,
+            white,
+            sequence: 0,
+            packet: Vec::with_capacity(DDP_MAX_DATA + 10),
+        })
+    }
+
+    fn channels(&self) -> usize {
+        match self.white {
+            Some(_) => 4,
+            None => 3,
+        }
+    }
+
+    /// Append the channels of the pixels to the packet
+    fn extend(packet: &mut Vec<u8>, pixels: &[Rgb], white: Option<WhiteExtraction>) {
+        for &rgb in pixels {
+            match white {
+                Some(white) => {
+                    let rgbw = white.apply(rgb);
+                    packet.extend([rgbw.r, rgbw.g, rgbw.b, rgbw.w]);
+                },
+                None => packet.extend([rgb.r, rgb.g, rgb.b]),
+            }
+        }
+    }
+
+    fn write_ddp(&mut self, pixels: &[Rgb]) -> Result<()> {
+        self.sequence = self.sequence % 15 + 1;
+        let channels = self.channels();
+        let data_type = if channels == 4 { DDP_RGBW } else { DDP_RGB };
+
+        let chunks = pixels.chunks(DDP_MAX_DATA / channels);
+        let count = chunks.len();
+        for (i, chunk) in chunks.enumerate() {
+            let flags = if i + 1 == count { DDP_VERSION | DDP_PUSH } else { DDP_VERSION };
+            let offset = (i * DDP_MAX_DATA) as u32;
+            let length = (chunk.len() * channels) as u16;
+
+            self.packet.clear();
+            self.packet.extend([flags, self.sequence, data_type, DDP_DESTINATION]);
+            self.packet.extend(offset.to_be_bytes());
+            self.packet.extend(length.to_be_bytes());
+            Self::extend(&mut self.packet, chunk, self.white);
+            self.socket.send(&self.packet)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_wled(&mut self, pixels: &[Rgb]) -> Result<()> {
+        if self.white.is_some() {
+            if pixels.len() > WLED_DRGBW_MAX {
+                return Err(Error::FrameLength { expected: WLED_DRGBW_MAX, actual: pixels.len() });
+            }
+
+            self.packet.clear();
+            self.packet.extend([WLED_DRGBW, WLED_TIMEOUT]);
+            Self::extend(&mut self.packet, pixels, self.white);
+            self.socket.send(&self.packet)?;
+            return Ok(());
+        }
+
+        if pixels.len() <= WLED_DRGB_MAX {
+            self.packet.clear();
+            self.packet.extend([WLED_DRGB, WLED_TIMEOUT]);
+            Self::extend(&mut self.packet, pixels, None);
+            self.socket.send(&self.packet)?;
+            return Ok(());
+        }
+
+        for (i, chunk) in pixels.chunks(WLED_DNRGB_MAX).enumerate() {
+            let start = (i * WLED_DNRGB_MAX) as u16;
+
+            self.packet.clear();
+            self.packet.extend([WLED_DNRGB, WLED_TIMEOUT]);
+            self.packet.extend(start.to_be_bytes());
+            Self::extend(&mut self.packet, chunk, None);
+            self.socket.send(&self.packet)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Output for NetworkOutput {
+    fn write(&mut self, pixels: &[Rgb]) -> Result<()> {
+        match self.protocol {
+            NetworkProtocol::Ddp => self.write_ddp(pixels),
+            NetworkProtocol::Wled => self.write_wled(pixels),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::UdpSocket;
+    use std::time::Duration;
+    use crate::{NetworkOutput, NetworkProtocol, Output, Rgb, WhiteExtraction};
+
+    fn listen() -> (UdpSocket, String) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        let address = socket.local_addr().unwrap().to_string();
+        (socket, address)
+    }
+
+    fn receive(socket: &UdpSocket) -> Vec<u8> {
+        let mut buf = [0u8; 2048];
+        let length = socket.recv(&mut buf).unwrap();
+        buf[..length].to_vec()
+    }
+
+    #[test]
+    fn test_ddp() {
+        let (socket, address) = listen();
+        let mut output = NetworkOutput::new(&address, NetworkProtocol::Ddp, None).unwrap();
+
+        // 500 LEDs don't fit in a single packet
+        output.write(&vec![Rgb::new(1, 2, 3); 500]).unwrap();
+        let first = receive(&socket);
+        let second = receive(&socket);
+
+        assert_eq!([0x40, 1, 0x0B, 1, 0, 0, 0, 0, 0x05, 0xA0], first[..10]);
+        assert_eq!([1, 2, 3], first[10..13]);
+        assert_eq!(10 + 1440, first.len());
+        assert_eq!([0x41, 1, 0x0B, 1, 0, 0, 0x05, 0xA0, 0, 60], second[..10]);
+        assert_eq!(10 + 60, second.len());
+    }
+
+    #[test]
+    fn test_ddp_rgbw() {
+        let (socket, address) = listen();
+        let mut output = NetworkOutput::new(&address, NetworkProtocol::Ddp, Some(WhiteExtraction::Min)).unwrap();
+
+        output.write(&[Rgb::new(255, 255, 255)]).unwrap();
+        assert_eq!([0x41, 1, 0x1B, 1, 0, 0, 0, 0, 0, 4, 0, 0, 0, 255], receive(&socket)[..]);
+    }
+
+    #[test]
+    fn test_wled() {
+        let (socket, address) = listen();
+        let mut output = NetworkOutput::new(&address, NetworkProtocol::Wled, None).unwrap();
+
+        output.write(&[Rgb::new(1, 2, 3), Rgb::new(4, 5, 6)]).unwrap();
+        assert_eq!([2, 255, 1, 2, 3, 4, 5, 6], receive(&socket)[..]);
+
+        // Too long for DRGB, so it is split up with DNRGB
+        output.write(&vec![Rgb::new(1, 2, 3); 500]).unwrap();
+        let first = receive(&socket);
+        let second = receive(&socket);
+        assert_eq!([4, 255, 0, 0, 1, 2, 3], first[..7]);
+        assert_eq!(4 + 489 * 3, first.len());
+        assert_eq!([4, 255, 0x01, 0xE9], second[..4]);
+        assert_eq!(4 + 11 * 3, second.len());
+    }
+
+    #[test]
+    fn test_address() {
+        assert!(NetworkOutput::new("127.0.0.1", NetworkProtocol::Wled, None).is_ok());
+        assert!(NetworkOutput::new("not a host", NetworkProtocol::Ddp, None).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::Instant;
+use crate::error::{Error, Result};
+use crate::output::Output;
+use crate::recording::{write_frame, RecordingHeader};
+use crate::Rgb;
+
+/// Records every frame with a timestamp in a compact binary format.
+/// The recording can be read with [crate::RecordingReader] and played back onto any output with [crate::play].
+#[derive(Debug)]
+pub struct RecorderOutput<W: Write + Send> {
+    writer: W,
+    white: bool,
+    /// Set once the first frame has been written
+    started: Option<(Instant, RecordingHeader)>,
+}
+
+impl RecorderOutput<File> {
+    /// Record to the file at `path`, replacing it if it exists
+    pub fn create<P: AsRef<Path>>(path: P, white: bool) -> Result<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        Ok(Self::new(file, white))
+    }
+}
+
+impl<W: Write + Send> RecorderOutput<W> {
+    /// Record to any writer. The white channel is only recorded if `white` is set.
+    pub fn new(writer: W, white: bool) -> Self {
+        Self {
+            writer,
+            white,
+            started: None,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + Send> Output for RecorderOutput<W> {
+    fn write(&mut self, pixels: &[Rgb]) -> Result<()> {
+        let (started, header) = match self.started {
+            Some(x) => x,
+            None => {
+                // The length of the strip is only known once the first frame is written
+                let header = RecordingHeader {
+                    length: pixels.len() as u16,
+                    channels: if self.white { 4 } else { 3 },
+                };
+                header.write(&mut self.writer)?;
+                *self.started.insert((Instant::now(), header))
+            }
+        };
+
+        if pixels.len() != header.length as usize {
+            return Err(Error::FrameLength { expected: header.length as usize, actual: pixels.len() });
+        }
+
+        write_frame(&mut self.writer, &header, started.elapsed(), pixels)
+    }
+}
@@ -0,0 +1,55 @@
+use std::io::Write;
+use spidev::{SpiModeFlags, SpidevOptions};
+use tracing::trace;
+use crate::chipset::Encoder;
+use crate::encoding::PixelFormat;
+use crate::error::Result;
+use crate::output::Output;
+use crate::{Rgb, Rgb16, Spidev};
+
+/// Writes to a strip connected to an SPI device
+#[derive(Debug)]
+pub struct SpiOutput {
+    spi: spidev::Spidev,
+    encoder: Encoder,
+}
+
+impl SpiOutput {
+    pub fn new(spidev: &Spidev, format: PixelFormat) -> Result<Self> {
+        let encoder = Encoder::new(format, spidev.speed_hz)?;
+
+        trace!("Opening {:?} at {} Hz", spidev.path, spidev.speed_hz);
+        let mut spi = spidev::Spidev::open(&spidev.path)?;
+        spi.configure(&SpidevOptions::new()
+            .bits_per_word(8)
+            .max_speed_hz(spidev.speed_hz)
+            .mode(SpiModeFlags::SPI_MODE_0)
+            .build())?;
+
+        Ok(Self {
+            spi,
+            encoder,
+        })
+    }
+}
+
+/// The whole frame has to go out in a single transfer,
+/// a pause between transfers would latch a partial frame
+fn transfer(spi: &mut spidev::Spidev, encoded: &[u8]) -> Result<()> {
+    spi.write_all(encoded)?;
+    Ok(())
+}
+
+impl Output for SpiOutput {
+    fn write(&mut self, pixels: &[Rgb]) -> Result<()> {
+        transfer(&mut self.spi, self.encoder.encode(pixels))
+    }
+
+    fn is_wide(&self) -> bool {
+        self.encoder.is_wide()
+    }
+
+    fn write_wide(&mut self, pixels: &[Rgb16]) -> Result<()> {
+        transfer(&mut self.spi, self.encoder.encode_wide(pixels))
+    }
+}
@@ -0,0 +1,97 @@
+use std::io::{self, Stdout, Write};
+use crate::error::Result;
+use crate::output::Output;
+use crate::Rgb;
+
+/// The number of LEDs per row if the terminal width is unknown
+const DEFAULT_WIDTH: usize = 80;
+
+/// Simulates a strip by drawing every LED as a truecolor block in the terminal.
+/// Each frame is drawn over the previous one, long strips wrap over multiple rows.
+#[derive(Debug)]
+pub struct TerminalOutput<W: Write + Send> {
+    writer: W,
+    width: usize,
+    /// The number of rows drawn by the previous frame
+    rows: usize,
+}
+
+impl TerminalOutput<Stdout> {
+    /// Draw to stdout, as wide as the `COLUMNS` environment variable says the terminal is
+    pub fn stdout() -> Self {
+        let width = std::env::var("COLUMNS").ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(DEFAULT_WIDTH);
+        Self::new(io::stdout()).with_width(width)
+    }
+}
+
+impl<W: Write + Send> TerminalOutput<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            width: DEFAULT_WIDTH,
+            rows: 0,
+        }
+    }
+
+    /// Set the number of LEDs drawn per row
+    pub fn with_width(self, width: usize) -> Self {
+        Self {
+            width: width.max(1),
+            ..self
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + Send> Output for TerminalOutput<W> {
+    fn write(&mut self, pixels: &[Rgb]) -> Result<()> {
+        let mut buf = String::new();
+        if self.rows > 0 {
+            // Move back to the start of the previous frame
+            buf.push_str(&format!("\x1b[{}A\r", self.rows));
+        }
+
+        for row in pixels.chunks(self.width) {
+            for pixel in row {
+                // A terminal has no white LED, mix it into the other channels
+                buf.push_str(&format!(
+                    "\x1b[38;2;{};{};{}m█",
+                    pixel.r.saturating_add(pixel.w),
+                    pixel.g.saturating_add(pixel.w),
+                    pixel.b.saturating_add(pixel.w),
+                ));
+            }
+            buf.push_str("\x1b[0m\x1b[K\n");
+        }
+
+        self.rows = pixels.chunks(self.width).len();
+        self.writer.write_all(buf.as_bytes())?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Output, Rgb, TerminalOutput};
+
+    #[test]
+    fn test_draws_in_place() {
+        let mut output = TerminalOutput::new(Vec::new()).with_width(2);
+        let pixels = [Rgb::new(255, 0, 0), Rgb::new(0, 255, 0), Rgb::new_rgbw(0, 0, 200, 100)];
+        output.write(&pixels).unwrap();
+        let first_len = output.writer.len();
+        output.write(&pixels).unwrap();
+
+        let drawn = String::from_utf8(output.into_inner()).unwrap();
+        let (first, second) = drawn.split_at(first_len);
+        assert!(first.starts_with("\x1b[38;2;255;0;0m█\x1b[38;2;0;255;0m█\x1b[0m\x1b[K\n"));
+        assert!(first.ends_with("\x1b[38;2;100;100;255m█\x1b[0m\x1b[K\n"));
+        assert!(second.starts_with("\x1b[2A\r"));
+    }
+}
@@ -0,0 +1,114 @@
+use serde::{Serialize, Deserialize};
+use crate::Rgb16;
+
+/// Model of the current drawn by the strip, with an optional limit.
+/// Frames exceeding the limit are scaled down proportionally before they are written.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PowerBudget {
+    /// Current drawn by a single color channel of one controllable section at full brightness, in mA.
+    /// The white channel of RGBW strips is counted as a channel too.
+    /// For strips with multiple LEDs per controller this should be multiplied accordingly.
+    pub milliamps_per_channel: f32,
+    /// Current drawn by each controllable section when it is turned off, in mA
+    pub idle_milliamps: f32,
+    /// The maximum current the whole strip may draw, in mA. No limit is applied if unset.
+    pub max_milliamps: Option<u32>,
+}
+
+impl Default for PowerBudget {
+    fn default() -> Self {
+        Self {
+            milliamps_per_channel: 20.0,
+            idle_milliamps: 1.0,
+            max_milliamps: None,
+        }
+    }
+}
+
+/// The estimated current draw of a frame
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PowerEstimate {
+    /// The current the frame would have drawn without a limit, in mA
+    pub requested_milliamps: f32,
+    /// The current the frame draws after limiting, in mA
+    pub milliamps: f32,
+}
+
+impl PowerEstimate {
+    pub fn is_limited(&self) -> bool {
+        self.milliamps < self.requested_milliamps
+    }
+}
+
+impl PowerBudget {
+    /// Estimate the current drawn when displaying the pixels
+    pub fn estimate(&self, pixels: &[Rgb16]) -> f32 {
+        self.channel_milliamps(pixels) + self.idle_milliamps * pixels.len() as f32
+    }
+
+    fn channel_milliamps(&self, pixels: &[Rgb16]) -> f32 {
+        let total = pixels.iter()
+            .map(|Rgb16 { r, g, b, w }| *r as u64 + *g as u64 + *b as u64 + *w as u64)
+            .sum::<u64>();
+        total as f32 / 65535.0 * self.milliamps_per_channel
+    }
+
+    /// Scale the pixels down so they fit within the budget
+    pub fn limit(&self, pixels: &mut [Rgb16]) -> PowerEstimate {
+        let requested = self.estimate(pixels);
+        let max = match self.max_milliamps {
+            Some(x) if requested > x as f32 => x as f32,
+            _ => return PowerEstimate { requested_milliamps: requested, milliamps: requested },
+        };
+
+        // The idle current can't be scaled, only what the channels draw
+        let idle = self.idle_milliamps * pixels.len() as f32;
+        let scale = ((max - idle) / self.channel_milliamps(pixels)).clamp(0.0, 1.0);
+        for Rgb16 { r, g, b, w } in pixels.iter_mut() {
+            // Rounding down guarantees we never end up over budget
+            *r = (*r as f32 * scale) as u16;
+            *g = (*g as f32 * scale) as u16;
+            *b = (*b as f32 * scale) as u16;
+            *w = (*w as f32 * scale) as u16;
+        }
+
+        PowerEstimate {
+            requested_milliamps: requested,
+            milliamps: self.estimate(pixels),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{PowerBudget, Rgb, Rgb16};
+
+    #[test]
+    fn test_estimate() {
+        let budget = PowerBudget { milliamps_per_channel: 20.0, idle_milliamps: 1.0, max_milliamps: None };
+        let pixels = vec![Rgb16::from(Rgb::new(255, 255, 255)); 10];
+        assert_eq!(610.0, budget.estimate(&pixels));
+    }
+
+    #[test]
+    fn test_limit() {
+        let budget = PowerBudget { milliamps_per_channel: 20.0, idle_milliamps: 0.0, max_milliamps: Some(300) };
+        let mut pixels = vec![Rgb16::from(Rgb::new(255, 255, 255)); 10];
+        let estimate = budget.limit(&mut pixels);
+
+        assert!(estimate.is_limited());
+        assert!(estimate.milliamps <= 300.0);
+        assert_eq!(Rgb::new(127, 127, 127), pixels[0].to_rgb());
+    }
+
+    #[test]
+    fn test_within_budget_is_untouched() {
+        let budget = PowerBudget { max_milliamps: Some(1000), ..PowerBudget::default() };
+        let mut pixels = vec![Rgb16::from(Rgb::new(255, 0, 0)); 10];
+        let estimate = budget.limit(&mut pixels);
+
+        assert!(!estimate.is_limited());
+        assert_eq!(Rgb::new(255, 0, 0), pixels[0].to_rgb());
+    }
+}
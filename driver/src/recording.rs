@@ -0,0 +1,180 @@
+//! A compact binary format for frames as they were written to a strip.
+//!
+//! A recording starts with a header:
+//! - the magic bytes `DLRC`
+//! - the format version, one byte
+//! - the number of channels per LED, one byte: 3 for RGB or 4 for RGBW
+//! - the number of LEDs, two bytes little endian
+//!
+//! Followed by any number of frames:
+//! - the milliseconds since the first frame, four bytes little endian
+//! - the channels of every LED, one byte per channel
+
+use std::io::{ErrorKind, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::error::{Error, Result};
+use crate::output::Output;
+use crate::Rgb;
+
+const MAGIC: &[u8; 4] = b"DLRC";
+const VERSION: u8 = 1;
+
+/// The header at the start of every recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordingHeader {
+    pub length: u16,
+    /// 3 for RGB or 4 for RGBW
+    pub channels: u8,
+}
+
+impl RecordingHeader {
+    pub(crate) fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION, self.channels])?;
+        writer.write_all(&self.length.to_le_bytes())?;
+        Ok(())
+    }
+
+    fn read<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut buf = [0u8; 8];
+        reader.read_exact(&mut buf)?;
+        if &buf[..4] != MAGIC {
+            return Err(Error::Recording("Not a recording".to_string()));
+        }
+        if buf[4] != VERSION {
+            return Err(Error::Recording(format!("Unsupported version {}", buf[4])));
+        }
+
+        let channels = buf[5];
+        if channels != 3 && channels != 4 {
+            return Err(Error::Recording(format!("Unsupported number of channels {channels}")));
+        }
+
+        Ok(Self {
+            channels,
+            length: u16::from_le_bytes([buf[6], buf[7]]),
+        })
+    }
+
+    pub(crate) fn frame_size(&self) -> usize {
+        self.length as usize * self.channels as usize
+    }
+}
+
+/// A single frame read from a recording
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedFrame {
+    /// The time since the first frame
+    pub timestamp: Duration,
+    pub pixels: Vec<Rgb>,
+}
+
+pub(crate) fn write_frame<W: Write>(writer: &mut W, header: &RecordingHeader, timestamp: Duration, pixels: &[Rgb]) -> Result<()> {
+    let mut buf = Vec::with_capacity(4 + header.frame_size());
+    buf.extend_from_slice(&(timestamp.as_millis() as u32).to_le_bytes());
+    for pixel in pixels {
+        buf.extend_from_slice(&[pixel.r, pixel.g, pixel.b]);
+        if header.channels == 4 {
+            buf.push(pixel.w);
+        }
+    }
+
+    writer.write_all(&buf)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads the frames from a recording one at a time
+#[derive(Debug)]
+pub struct RecordingReader<R: Read> {
+    reader: R,
+    header: RecordingHeader,
+}
+
+impl<R: Read> RecordingReader<R> {
+    /// Read the header of the recording
+    pub fn new(mut reader: R) -> Result<Self> {
+        let header = RecordingHeader::read(&mut reader)?;
+        Ok(Self {
+            reader,
+            header,
+        })
+    }
+
+    pub fn header(&self) -> RecordingHeader {
+        self.header
+    }
+
+    /// Read the next frame, `None` at the end of the recording
+    pub fn next_frame(&mut self) -> Result<Option<RecordedFrame>> {
+        let mut timestamp = [0u8; 4];
+        match self.reader.read_exact(&mut timestamp) {
+            Ok(_) => {},
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+
+        let mut buf = vec![0u8; self.header.frame_size()];
+        self.reader.read_exact(&mut buf)
+            .map_err(|_| Error::Recording("Recording ends halfway through a frame".to_string()))?;
+
+        let pixels = buf.chunks(self.header.channels as usize)
+            .map(|x| Rgb::new_rgbw(x[0], x[1], x[2], x.get(3).copied().unwrap_or(0)))
+            .collect();
+
+        Ok(Some(RecordedFrame {
+            timestamp: Duration::from_millis(u32::from_le_bytes(timestamp) as u64),
+            pixels,
+        }))
+    }
+}
+
+/// Write every frame of a recording to an output, keeping the original timing.
+/// A `speed` of 2.0 plays the recording twice as fast.
+/// Frames are written as they were recorded, without any correction applied.
+pub fn play<R: Read>(recording: &mut RecordingReader<R>, output: &mut dyn Output, speed: f32) -> Result<()> {
+    let speed = if speed > 0.0 { speed } else { 1.0 };
+    let started = Instant::now();
+    while let Some(frame) = recording.next_frame()? {
+        let due = started + frame.timestamp.div_f32(speed);
+        thread::sleep(due.saturating_duration_since(Instant::now()));
+        output.write(&frame.pixels)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{play, Error, MemoryOutput, Output, RecorderOutput, RecordingHeader, RecordingReader, Rgb};
+
+    #[test]
+    fn test_record_and_play() {
+        let frames = [
+            vec![Rgb::new(1, 2, 3), Rgb::new_rgbw(4, 5, 6, 7)],
+            vec![Rgb::off(), Rgb::new(255, 0, 0)],
+        ];
+
+        let mut recorder = RecorderOutput::new(Vec::new(), true);
+        frames.iter().for_each(|x| recorder.write(x).unwrap());
+        let recording = recorder.into_inner();
+        assert_eq!(8 + 2 * (4 + 8), recording.len());
+
+        let mut reader = RecordingReader::new(recording.as_slice()).unwrap();
+        assert_eq!(RecordingHeader { length: 2, channels: 4 }, reader.header());
+
+        let output = MemoryOutput::new();
+        play(&mut reader, &mut output.clone(), 100.0).unwrap();
+        let played = output.frames();
+        assert_eq!(2, played.len());
+        assert_eq!(frames[0], played[0].pixels());
+        assert_eq!(frames[1], played[1].pixels());
+    }
+
+    #[test]
+    fn test_not_a_recording() {
+        let result = RecordingReader::new(b"RIFF\x01\x03\x02\x00".as_slice());
+        assert!(matches!(result, Err(Error::Recording(_))));
+    }
+}
@@ -1,41 +1,151 @@
 use crate::error::{Error, Result};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tracing::trace;
 
-#[derive(Debug)]
-pub struct Spidev(pub(crate) String);
+/// An SPI device node, e.g. `/dev/spidev0.0`, and the clock speed to drive it at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spidev {
+    pub path: PathBuf,
+    /// The SPI bus, parsed from the device name. `None` if the name isn't `spidev<bus>.<cs>`.
+    pub bus: Option<u16>,
+    /// The chip select line on the bus, parsed from the device name
+    pub chip_select: Option<u16>,
+    pub speed_hz: u32,
+}
 
 impl Spidev {
+    /// The SPI clock speed used unless configured otherwise
+    pub const DEFAULT_SPEED_HZ: u32 = 15_600_000;
+
+    /// Use the first SPI device, ordered by bus and chip select
     pub fn new() -> Result<Self> {
-        trace!("Reading directory '/dev', scanning for spi devices");
-        let rd = fs::read_dir("/dev/")?;
-        for entry in rd {
+        Self::list()?
+            .into_iter()
+            .next()
+            .ok_or(Error::NoSpiDev)
+    }
+
+    pub fn new_with_name<S: AsRef<str>>(name: S) -> Result<Self> {
+        trace!("Checking if provided spidev '{}' exists", name.as_ref());
+        let path = Path::new(name.as_ref());
+        if !path.exists() {
+            return Err(Error::NoSpiDev);
+        }
+
+        Ok(Self::from_path(path.to_path_buf()))
+    }
+
+    /// Find a device either by path, e.g. `/dev/spidev0.1`, or by bus and chip select, e.g. `0.1`
+    pub fn find<S: AsRef<str>>(device: S) -> Result<Self> {
+        match parse_bus_cs(device.as_ref()) {
+            Some((bus, chip_select)) => Self::select(bus, chip_select),
+            None => Self::new_with_name(device),
+        }
+    }
+
+    /// All SPI devices in `/dev`, ordered by bus and chip select
+    pub fn list() -> Result<Vec<Self>> {
+        Self::list_in("/dev")
+    }
+
+    /// All SPI devices in the directory `root`, ordered by bus and chip select
+    pub fn list_in<P: AsRef<Path>>(root: P) -> Result<Vec<Self>> {
+        trace!("Reading directory {:?}, scanning for spi devices", root.as_ref());
+        let mut devices = Vec::new();
+        for entry in fs::read_dir(root)? {
             let entry = entry?;
             if entry.file_type()?.is_dir() {
                 continue;
             }
 
-            let path = entry.path();
-            let path_string = path.to_string_lossy();
-
-            if path_string.contains("spi") {
-                trace!("Found spi device {:?}", path_string);
-                return Ok(Self(path_string.to_string()))
+            if entry.file_name().to_string_lossy().starts_with("spidev") {
+                trace!("Found spi device {:?}", entry.path());
+                devices.push(Self::from_path(entry.path()));
             }
         }
 
-        trace!("Found no spi device");
-        Err(Error::NoSpiDev)
+        // Devices with names we can't parse go last
+        devices.sort_by_key(|x| (x.bus.is_none(), x.bus, x.chip_select, x.path.clone()));
+        Ok(devices)
     }
 
-    pub fn new_with_name<S: AsRef<str>>(name: S) -> Result<Self> {
-        trace!("Checking if provided spidev '{}' exists", name.as_ref());
-        let path = Path::new(name.as_ref());
-        if !path.exists() {
-            return Err(Error::NoSpiDev);
+    /// The SPI device on `bus` using chip select `chip_select`, in `/dev`
+    pub fn select(bus: u16, chip_select: u16) -> Result<Self> {
+        Self::select_in("/dev", bus, chip_select)
+    }
+
+    /// The SPI device on `bus` using chip select `chip_select`, in the directory `root`
+    pub fn select_in<P: AsRef<Path>>(root: P, bus: u16, chip_select: u16) -> Result<Self> {
+        Self::list_in(root)?
+            .into_iter()
+            .find(|x| x.bus == Some(bus) && x.chip_select == Some(chip_select))
+            .ok_or(Error::NoSpiDev)
+    }
+
+    /// Drive the device at a different SPI clock speed
+    pub fn with_speed(self, speed_hz: u32) -> Self {
+        Self {
+            speed_hz,
+            ..self
+        }
+    }
+
+    fn from_path(path: PathBuf) -> Self {
+        let bus_cs = path.file_name()
+            .and_then(|x| x.to_str())
+            .and_then(|x| x.strip_prefix("spidev"))
+            .and_then(parse_bus_cs);
+
+        Self {
+            path,
+            bus: bus_cs.map(|(bus, _)| bus),
+            chip_select: bus_cs.map(|(_, cs)| cs),
+            speed_hz: Self::DEFAULT_SPEED_HZ,
+        }
+    }
+}
+
+/// Parse `<bus>.<cs>`, e.g. `0.1`
+fn parse_bus_cs(s: &str) -> Option<(u16, u16)> {
+    let (bus, cs) = s.split_once('.')?;
+    Some((bus.parse().ok()?, cs.parse().ok()?))
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::PathBuf;
+    use crate::Spidev;
+
+    fn fake_dev(name: &str, files: &[&str]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("deskled-test-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("spidev-dir")).unwrap();
+        for file in files {
+            fs::write(root.join(file), []).unwrap();
         }
+        root
+    }
+
+    #[test]
+    fn test_list() {
+        let root = fake_dev("list", &["spidev1.0", "spidev0.1", "spidev0.0", "tty0", "spi-weird"]);
+        let devices = Spidev::list_in(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        let parsed = devices.iter().map(|x| (x.bus, x.chip_select)).collect::<Vec<_>>();
+        assert_eq!(vec![(Some(0), Some(0)), (Some(0), Some(1)), (Some(1), Some(0))], parsed);
+    }
+
+    #[test]
+    fn test_select() {
+        let root = fake_dev("select", &["spidev0.0", "spidev0.1"]);
+        let selected = Spidev::select_in(&root, 0, 1);
+        let missing = Spidev::select_in(&root, 1, 0);
+        fs::remove_dir_all(&root).unwrap();
 
-        Ok(Self(name.as_ref().to_string()))
+        assert_eq!(root.join("spidev0.1"), selected.unwrap().path);
+        assert!(missing.is_err());
     }
 }
@@ -0,0 +1,190 @@
+use std::time::Duration;
+use crate::effect::{EffectKind, EffectParams};
+use crate::error::{Error, Result};
+use crate::transition::Transition;
+use crate::{Color, Engine, Rgb};
+
+/// The name of the strip when only a single strip is used
+pub const DEFAULT_STRIP: &str = "strip";
+
+/// One or more strips, each driven by their own [Engine].
+/// Strips can be controlled individually with [Strips::get] or [Strips::only],
+/// or all at once through the methods on this type.
+/// Cloning is cheap, the engines are shared.
+#[derive(Debug, Clone)]
+pub struct Strips {
+    strips: Vec<(String, Engine)>,
+}
+
+impl Strips {
+    /// Names must be unique
+    pub fn new(strips: Vec<(String, Engine)>) -> Result<Self> {
+        for (i, (name, _)) in strips.iter().enumerate() {
+            if strips[..i].iter().any(|(x, _)| x == name) {
+                return Err(Error::Strip(format!("There is more than one strip named '{name}'")));
+            }
+        }
+
+        Ok(Self {
+            strips,
+        })
+    }
+
+    /// A single strip named [DEFAULT_STRIP]
+    pub fn single(engine: Engine) -> Self {
+        Self {
+            strips: vec![(DEFAULT_STRIP.to_string(), engine)],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.strips.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strips.is_empty()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.strips.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Engine> {
+        self.strips.iter()
+            .find(|(x, _)| x == name)
+            .map(|(_, engine)| engine)
+            .ok_or_else(|| Error::UnknownStrip(name.to_string()))
+    }
+
+    /// Only the strip with this name, so it can be controlled like a group
+    pub fn only(&self, name: &str) -> Result<Self> {
+        let engine = self.get(name)?.clone();
+        Ok(Self {
+            strips: vec![(name.to_string(), engine)],
+        })
+    }
+
+    /// See [Engine::with_transition]
+    pub fn with_transition(&self, transition: Transition) -> Self {
+        Self {
+            strips: self.strips.iter()
+                .map(|(name, engine)| (name.clone(), engine.with_transition(transition)))
+                .collect(),
+        }
+    }
+
+    fn for_each<F: Fn(&Engine) -> Result<()>>(&self, f: F) -> Result<()> {
+        self.strips.iter().try_for_each(|(_, engine)| f(engine))
+    }
+
+    /// See [Engine::set_rgb]
+    pub fn set_rgb(&self, rgb: Rgb) -> Result<()> {
+        self.for_each(|x| x.set_rgb(rgb))
+    }
+
+    /// See [Engine::set_kelvin]. Every strip converts the temperature with its own calibration.
+    pub fn set_kelvin(&self, kelvin: u16) -> Result<()> {
+        self.for_each(|x| x.set_kelvin(kelvin))
+    }
+
+    /// See [Engine::set_color]
+    pub fn set_color(&self, color: Color) -> Result<()> {
+        self.for_each(|x| x.set_color(color))
+    }
+
+    /// See [Engine::set_brightness]
+    pub fn set_brightness(&self, brightness: f32) -> Result<()> {
+        self.for_each(|x| x.set_brightness(brightness))
+    }
+
+    /// See [Engine::start_effect]. Every strip runs its own instance of the effect.
+    pub fn start_effect(&self, kind: EffectKind, params: &EffectParams) -> Result<()> {
+        self.for_each(|x| x.start_effect(kind, params))
+    }
+
+    /// See [Engine::stop_effect]
+    pub fn stop_effect(&self) -> Result<()> {
+        self.for_each(Engine::stop_effect)
+    }
+
+    /// See [Engine::set_transition]
+    pub fn set_transition(&self, transition: Transition) -> Result<()> {
+        self.for_each(|x| x.set_transition(transition))
+    }
+
+    /// See [Engine::set_refresh_interval]
+    pub fn set_refresh_interval(&self, interval: Option<Duration>) -> Result<()> {
+        self.for_each(|x| x.set_refresh_interval(interval))
+    }
+
+    /// See [Engine::flush]. Every strip is flushed, the first error is returned.
+    pub fn flush(&self) -> Result<()> {
+        self.strips.iter()
+            .map(|(_, engine)| engine.flush())
+            .fold(Ok(()), Result::and)
+    }
+
+    /// See [Engine::wait_for_transition]
+    pub fn wait_for_transition(&self) -> Result<()> {
+        self.for_each(Engine::wait_for_transition)
+    }
+
+    /// The effect running on every strip, if they all run the same one
+    pub fn effect(&self) -> Option<EffectKind> {
+        let mut effects = self.strips.iter().map(|(_, engine)| engine.effect());
+        let first = effects.next()??;
+        effects.all(|x| x == Some(first)).then_some(first)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Color, Driver, EffectKind, EffectParams, Engine, Error, KelvinCalibration, MemoryOutput, Rgb, Strips};
+
+    #[test]
+    fn test_individual_and_group() {
+        let left = MemoryOutput::new();
+        let right = MemoryOutput::new();
+        let strips = Strips::new(vec![
+            ("left".to_string(), Engine::spawn(Driver::with_output(Box::new(left.clone()), 2), 100)),
+            ("right".to_string(), Engine::spawn(Driver::with_output(Box::new(right.clone()), 3), 100)),
+        ]).unwrap();
+
+        strips.set_rgb(Rgb::new(255, 0, 0)).unwrap();
+        strips.only("right").unwrap().set_rgb(Rgb::new(0, 0, 255)).unwrap();
+        strips.wait_for_transition().unwrap();
+
+        assert_eq!(vec![Rgb::new(255, 0, 0); 2], left.last().unwrap().pixels());
+        assert_eq!(vec![Rgb::new(0, 0, 255); 3], right.last().unwrap().pixels());
+        assert!(matches!(strips.get("middle"), Err(Error::UnknownStrip(_))));
+
+        strips.get("left").unwrap().start_effect(EffectKind::Fire, &EffectParams::default()).unwrap();
+        assert_eq!(None, strips.effect());
+        strips.start_effect(EffectKind::Fire, &EffectParams::default()).unwrap();
+        assert_eq!(Some(EffectKind::Fire), strips.effect());
+    }
+
+    #[test]
+    fn test_kelvin_per_strip() {
+        let plain = MemoryOutput::new();
+        let rgbw = MemoryOutput::new();
+        let mut driver = Driver::with_output(Box::new(rgbw.clone()), 1);
+        driver.set_kelvin_calibration(KelvinCalibration { points: Vec::new(), white_led_kelvin: Some(2700) });
+        let strips = Strips::new(vec![
+            ("plain".to_string(), Engine::spawn(Driver::with_output(Box::new(plain.clone()), 1), 100)),
+            ("rgbw".to_string(), Engine::spawn(driver, 100)),
+        ]).unwrap();
+
+        strips.set_color(Color::Kelvin(2700)).unwrap();
+        strips.flush().unwrap();
+        assert_eq!(Rgb::from_kelvin(2700), plain.last().unwrap()[0]);
+        assert_eq!(Rgb::new_rgbw(0, 0, 0, 255), rgbw.last().unwrap()[0]);
+    }
+
+    #[test]
+    fn test_unique_names() {
+        let engine = Engine::spawn(Driver::with_output(Box::new(MemoryOutput::new()), 1), 100);
+        let result = Strips::new(vec![("desk".to_string(), engine.clone()), ("desk".to_string(), engine)]);
+        assert!(matches!(result, Err(Error::Strip(_))));
+    }
+}
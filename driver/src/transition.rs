@@ -0,0 +1,123 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use serde::{Serialize, Deserialize};
+use crate::error::Error;
+
+/// How a transition progresses over its duration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Easing {
+    Linear,
+    /// Starts slow, ends fast
+    EaseIn,
+    /// Starts fast, ends slow
+    EaseOut,
+    /// Starts and ends slow
+    #[default]
+    EaseInOut,
+}
+
+impl Easing {
+    /// Map linear progress in the range `0.0..=1.0` to eased progress
+    pub fn apply(&self, progress: f32) -> f32 {
+        let t = progress.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t * t,
+            Self::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Self::EaseInOut => if t < 0.5 {
+                4.0 * t * t * t
+            } else {
+                1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+            },
+        }
+    }
+}
+
+impl FromStr for Easing {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "linear" => Self::Linear,
+            "ease-in" => Self::EaseIn,
+            "ease-out" => Self::EaseOut,
+            "ease-in-out" => Self::EaseInOut,
+            _ => return Err(Error::Easing(s.to_string()))
+        })
+    }
+}
+
+impl fmt::Display for Easing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Linear => "linear",
+            Self::EaseIn => "ease-in",
+            Self::EaseOut => "ease-out",
+            Self::EaseInOut => "ease-in-out",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// How changes in color and brightness are faded in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transition {
+    pub duration_ms: u64,
+    pub easing: Easing,
+}
+
+impl Default for Transition {
+    fn default() -> Self {
+        Self {
+            duration_ms: 500,
+            easing: Easing::default(),
+        }
+    }
+}
+
+impl Transition {
+    /// Changes are applied immediately
+    pub const NONE: Self = Self {
+        duration_ms: 0,
+        easing: Easing::Linear,
+    };
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.duration_ms)
+    }
+
+    /// The eased progress of the transition `elapsed` after it started, in the range `0.0..=1.0`
+    pub fn progress(&self, elapsed: Duration) -> f32 {
+        if self.duration_ms == 0 {
+            return 1.0;
+        }
+
+        self.easing.apply(elapsed.as_secs_f32() / self.duration().as_secs_f32())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use crate::{Easing, Transition};
+
+    #[test]
+    fn test_easing_endpoints() {
+        for easing in [Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut] {
+            assert_eq!(0.0, easing.apply(0.0));
+            assert_eq!(1.0, easing.apply(1.0));
+            assert!((easing.apply(0.5) - 0.5).abs() <= 0.4);
+        }
+    }
+
+    #[test]
+    fn test_progress() {
+        let transition = Transition { duration_ms: 1000, easing: Easing::Linear };
+        assert_eq!(0.5, transition.progress(Duration::from_millis(500)));
+        assert_eq!(1.0, transition.progress(Duration::from_secs(2)));
+        assert_eq!(1.0, Transition::NONE.progress(Duration::ZERO));
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Serialize, Deserialize};
+use crate::error::{Error, Result};
+
+/// The name of the zone covering the whole strip, used when no zones are configured
+pub const DEFAULT_ZONE: &str = "strip";
+
+/// A named, contiguous range of LEDs on the strip, which can be colored independently
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Zone {
+    pub name: String,
+    /// Index of the first LED in the zone
+    pub start: u16,
+    /// Amount of LEDs in the zone
+    pub length: u16,
+    /// Whether index `0` of the zone is its last LED on the strip,
+    /// e.g. when the strip runs towards you under one surface and away from you under another
+    #[serde(default)]
+    pub reversed: bool,
+}
+
+impl Zone {
+    /// A zone covering the whole strip
+    pub fn whole(length: u16) -> Self {
+        Self {
+            name: DEFAULT_ZONE.to_string(),
+            start: 0,
+            length,
+            reversed: false,
+        }
+    }
+
+    /// The index on the strip of the LED at `index` within the zone
+    pub fn strip_index(&self, index: u16) -> usize {
+        let offset = if self.reversed {
+            self.length - 1 - index
+        } else {
+            index
+        };
+
+        (self.start + offset) as usize
+    }
+
+    pub(crate) fn validate(zones: &[Zone], strip_length: u16) -> Result<()> {
+        for (i, zone) in zones.iter().enumerate() {
+            if zone.start as u32 + zone.length as u32 > strip_length as u32 {
+                return Err(Error::Zone(format!("Zone '{}' extends beyond the end of the strip", zone.name)));
+            }
+
+            if zones[..i].iter().any(|x| x.name == zone.name) {
+                return Err(Error::Zone(format!("Zone '{}' is defined more than once", zone.name)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Zone;
+
+    fn zone(start: u16, length: u16, reversed: bool) -> Zone {
+        Zone { name: "test".to_string(), start, length, reversed }
+    }
+
+    #[test]
+    fn test_strip_index() {
+        assert_eq!(5, zone(5, 3, false).strip_index(0));
+        assert_eq!(7, zone(5, 3, true).strip_index(0));
+        assert_eq!(5, zone(5, 3, true).strip_index(2));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(Zone::validate(&[zone(0, 10, false)], 10).is_ok());
+        assert!(Zone::validate(&[zone(5, 10, false)], 10).is_err());
+        assert!(Zone::validate(&[zone(0, 2, false), zone(2, 2, false)], 10).is_err());
+    }
+}
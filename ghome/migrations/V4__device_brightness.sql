@@ -0,0 +1,15 @@
+CREATE TABLE device_brightness (
+    brightness INT NOT NULL
+);
+
+-- The brightness used to be applied to the stored color.
+-- Store it on its own, and the color at full brightness.
+-- Colors too dim to round to 1% are kept at 1%, only black is 0%.
+INSERT INTO device_brightness (brightness)
+    SELECT IF(GREATEST(r, g, b) = 0, 0, GREATEST(1, ROUND(GREATEST(r, g, b) * 100 / 255))) FROM device_color;
+
+UPDATE device_color, device_brightness SET
+    device_color.r = LEAST(255, ROUND(device_color.r * 100 / device_brightness.brightness)),
+    device_color.g = LEAST(255, ROUND(device_color.g * 100 / device_brightness.brightness)),
+    device_color.b = LEAST(255, ROUND(device_color.b * 100 / device_brightness.brightness))
+    WHERE device_brightness.brightness > 0;
@@ -0,0 +1,5 @@
+-- Every strip can be controlled individually, so state is stored per Google device.
+-- Device '0' is all strips together, which is all there used to be.
+ALTER TABLE device_color ADD COLUMN device VARCHAR(64) NOT NULL DEFAULT '0';
+ALTER TABLE device_state ADD COLUMN device VARCHAR(64) NOT NULL DEFAULT '0';
+ALTER TABLE device_brightness ADD COLUMN device VARCHAR(64) NOT NULL DEFAULT '0';
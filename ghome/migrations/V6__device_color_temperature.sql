@@ -0,0 +1,2 @@
+-- Set if the color is a color temperature. r, g and b then hold an approximation of it.
+ALTER TABLE device_color ADD COLUMN kelvin INT NULL;
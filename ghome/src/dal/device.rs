@@ -1,9 +1,12 @@
 use std::str::Chars;
-use color_space::ToRgb;
-use mysql::{Params, params, Row, Transaction};
+use mysql::{params, Row, Transaction};
 use mysql::prelude::Queryable;
 use crate::WebResult;
 
+/// The Google device controlling every strip at once.
+/// Its state is also stored for every individual strip when there is more than one.
+pub const GROUP_DEVICE: &str = "0";
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Rgb {
     pub r: u8,
@@ -12,23 +15,6 @@ pub struct Rgb {
 }
 
 impl Rgb {
-    pub fn get_brightness(&self) -> u8 {
-        let rgb = color_space::Rgb::new(self.r as f64, self.g as f64, self.b as f64);
-        let hsv = color_space::Hsv::from(rgb);
-        (hsv.v * 100f64).floor() as u8
-    }
-
-    pub fn set_brightness(&mut self, brightness: u8) {
-        let rgb = color_space::Rgb::new(self.r as f64, self.g as f64, self.b as f64);
-        let mut hsv = color_space::Hsv::from(rgb);
-
-        hsv.v = brightness as f64 / 100f64;
-        let rgb = hsv.to_rgb();
-        self.r = rgb.r.floor() as u8;
-        self.g = rgb.g.floor() as u8;
-        self.b = rgb.b.floor() as u8;
-    }
-
     pub fn off() -> Self {
         Self {
             r: 0,
@@ -71,9 +57,62 @@ impl Rgb {
     }
 }
 
+impl From<Rgb> for driver::Rgb {
+    fn from(rgb: Rgb) -> Self {
+        driver::Rgb::new(rgb.r, rgb.g, rgb.b)
+    }
+}
+
+/// The color of a device, as it was set
+#[derive(Debug, Clone, PartialEq)]
+pub enum Color {
+    Rgb(Rgb),
+    /// A color temperature in Kelvin
+    Kelvin(u16),
+}
+
+impl Color {
+    pub fn is_off(&self) -> bool {
+        match self {
+            Self::Rgb(rgb) => rgb.is_off(),
+            Self::Kelvin(_) => false,
+        }
+    }
+
+    /// The closest RGB color, for anything which can't handle color temperatures
+    pub fn to_rgb(&self) -> Rgb {
+        match self {
+            Self::Rgb(rgb) => rgb.clone(),
+            Self::Kelvin(kelvin) => {
+                let rgb = driver::Rgb::from_kelvin(*kelvin);
+                Rgb { r: rgb.r, g: rgb.g, b: rgb.b }
+            }
+        }
+    }
+}
+
+impl From<Color> for driver::Color {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Rgb(rgb) => driver::Color::Rgb(rgb.into()),
+            Color::Kelvin(kelvin) => driver::Color::Kelvin(kelvin),
+        }
+    }
+}
+
+impl From<driver::Color> for Color {
+    /// The white channel of RGBW colors can't be stored and is dropped
+    fn from(color: driver::Color) -> Self {
+        match color {
+            driver::Color::Rgb(rgb) => Color::Rgb(Rgb { r: rgb.r, g: rgb.g, b: rgb.b }),
+            driver::Color::Kelvin(kelvin) => Color::Kelvin(kelvin),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::dal::device::Rgb;
+    use crate::dal::device::{Color, Rgb};
 
     #[test]
     fn test_rgb_to_spectrum_rgb() {
@@ -84,45 +123,70 @@ mod test {
     fn test_spectrum_rgb_to_rgb() {
         assert_eq!(Rgb { r: 255, g: 0, b: 255 }, Rgb::from_spectrum_rgb(16711935))
     }
+
+    #[test]
+    fn test_kelvin_to_rgb() {
+        assert_eq!(Rgb { r: 255, g: 255, b: 255 }, Color::Kelvin(6600).to_rgb());
+        assert!(!Color::Kelvin(2700).is_off());
+    }
 }
 
-pub fn get_rgb(tx: &mut Transaction) -> WebResult<Option<Rgb>> {
-    let row: Row = match tx.exec_first("SELECT r,g,b FROM device_color", Params::Empty)? {
+pub fn get_color(tx: &mut Transaction, device: &str) -> WebResult<Option<Color>> {
+    let row: Row = match tx.exec_first("SELECT r,g,b,kelvin FROM device_color WHERE device = :device", params! {
+        "device" => device
+    })? {
         Some(x) => x,
         None => return Ok(None)
     };
 
+    let kelvin: Option<u16> = row.get("kelvin").unwrap();
+    if let Some(kelvin) = kelvin {
+        return Ok(Some(Color::Kelvin(kelvin)));
+    }
+
     let r: u8 = row.get("r").unwrap();
     let g: u8 = row.get("g").unwrap();
     let b: u8 = row.get("b").unwrap();
 
-    Ok(Some(Rgb {
+    Ok(Some(Color::Rgb(Rgb {
         r,
         g,
         b
-    }))
+    })))
 }
 
-pub fn set_rgb(tx: &mut Transaction, rgb: Rgb) -> WebResult<()> {
-    if get_rgb(tx)?.is_some() {
-        tx.exec_drop("UPDATE device_color SET r = :r, g = :g, b = :b", params! {
+pub fn set_color(tx: &mut Transaction, device: &str, color: Color) -> WebResult<()> {
+    let rgb = color.to_rgb();
+    let kelvin = match color {
+        Color::Kelvin(kelvin) => Some(kelvin),
+        Color::Rgb(_) => None,
+    };
+
+    if get_color(tx, device)?.is_some() {
+        tx.exec_drop("UPDATE device_color SET r = :r, g = :g, b = :b, kelvin = :kelvin WHERE device = :device", params! {
             "r" => rgb.r,
             "g" => rgb.g,
-            "b" => rgb.b
+            "b" => rgb.b,
+            "kelvin" => kelvin,
+            "device" => device
         })?;
     } else {
-        tx.exec_drop("INSERT INTO device_color (r, g, b) VALUES (:r, :g, :b)", params! {
+        tx.exec_drop("INSERT INTO device_color (r, g, b, kelvin, device) VALUES (:r, :g, :b, :kelvin, :device)", params! {
             "r" => rgb.r,
             "g" => rgb.g,
-            "b" => rgb.b
+            "b" => rgb.b,
+            "kelvin" => kelvin,
+            "device" => device
         })?;
     }
 
     Ok(())
 }
 
-pub fn get_state(tx: &mut Transaction) -> WebResult<Option<bool>> {
-    let row: Row = match tx.exec_first("SELECT off FROM device_state", Params::Empty)? {
+pub fn get_state(tx: &mut Transaction, device: &str) -> WebResult<Option<bool>> {
+    let row: Row = match tx.exec_first("SELECT off FROM device_state WHERE device = :device", params! {
+        "device" => device
+    })? {
         Some(x) => x,
         None => return Ok(None)
     };
@@ -131,16 +195,47 @@ pub fn get_state(tx: &mut Transaction) -> WebResult<Option<bool>> {
     Ok(Some(!off))
 }
 
-pub fn set_state(tx: &mut Transaction, on: bool) -> WebResult<()> {
-    if get_state(tx)?.is_some() {
-        tx.exec_drop("UPDATE device_state SET off = :off", params! {
-            "off" => !on
+pub fn set_state(tx: &mut Transaction, device: &str, on: bool) -> WebResult<()> {
+    if get_state(tx, device)?.is_some() {
+        tx.exec_drop("UPDATE device_state SET off = :off WHERE device = :device", params! {
+            "off" => !on,
+            "device" => device
         })?;
     } else {
-        tx.exec_drop("INSERT INTO device_state (off) VALUES (:off)", params! {
-            "off" => !on
+        tx.exec_drop("INSERT INTO device_state (off, device) VALUES (:off, :device)", params! {
+            "off" => !on,
+            "device" => device
         })?;
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// The brightness in percent
+pub fn get_brightness(tx: &mut Transaction, device: &str) -> WebResult<Option<u8>> {
+    let row: Row = match tx.exec_first("SELECT brightness FROM device_brightness WHERE device = :device", params! {
+        "device" => device
+    })? {
+        Some(x) => x,
+        None => return Ok(None)
+    };
+
+    let brightness: u8 = row.get("brightness").unwrap();
+    Ok(Some(brightness))
+}
+
+pub fn set_brightness(tx: &mut Transaction, device: &str, brightness: u8) -> WebResult<()> {
+    if get_brightness(tx, device)?.is_some() {
+        tx.exec_drop("UPDATE device_brightness SET brightness = :brightness WHERE device = :device", params! {
+            "brightness" => brightness,
+            "device" => device
+        })?;
+    } else {
+        tx.exec_drop("INSERT INTO device_brightness (brightness, device) VALUES (:brightness, :device)", params! {
+            "brightness" => brightness,
+            "device" => device
+        })?;
+    }
+
+    Ok(())
+}
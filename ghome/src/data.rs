@@ -1,6 +1,6 @@
 use actix_web::web;
+use driver::Strips;
 use mysql::Pool;
-use crate::dal::device::Rgb;
 
 pub(crate) type WebData = web::Data<AppData>;
 
@@ -8,7 +8,7 @@ pub(crate) type WebData = web::Data<AppData>;
 pub struct AppData {
     pub config: Config,
     pub pool: Pool,
-    pub driver: tokio::sync::mpsc::Sender<Rgb>
+    pub driver: Strips,
 }
 
 #[derive(Debug, Clone)]
@@ -17,9 +17,10 @@ pub struct Config {
     pub oauth2_client_secret: String,
     pub login_username: String,
     pub login_password: String,
-    pub led_length: u16,
     pub mysql_host: String,
     pub mysql_password: String,
     pub mysql_username: String,
     pub mysql_database: String,
+    /// The local address the control API listens on
+    pub control_address: String,
 }
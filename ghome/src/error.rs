@@ -1,3 +1,4 @@
+use actix_web::error::BlockingError;
 use actix_web::http::StatusCode;
 use actix_web::ResponseError;
 use thiserror::Error;
@@ -20,6 +21,12 @@ pub enum Error {
     SerdeJson(#[from] serde_json::Error),
     #[error("Bad Request")]
     BadRequest,
+    #[error("Not Found")]
+    NotFound,
+    #[error("{0}")]
+    Driver(#[from] driver::Error),
+    #[error("{0}")]
+    Blocking(#[from] BlockingError),
 }
 
 impl ResponseError for Error {
@@ -32,6 +39,9 @@ impl ResponseError for Error {
             Self::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::SerdeJson(_) => StatusCode::BAD_REQUEST,
             Self::BadRequest => StatusCode::BAD_REQUEST,
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::Driver(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::Blocking(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
@@ -0,0 +1,130 @@
+use actix_web::web;
+use actix_web::web::{Json, Path, ServiceConfig};
+use driver::{Color, EffectKind, EffectParams, Rgb, Strips, Transition};
+use mysql::{Transaction, TxOpts};
+use serde::{Serialize, Deserialize};
+use tracing::instrument;
+use crate::dal::device::{get_brightness, get_color, get_state};
+use crate::data::WebData;
+use crate::error::Error;
+use crate::routable::Routable;
+use crate::state::{self, affected_devices, device_strips, StripChanges};
+use crate::WebResult;
+
+/// The API the CLI controls the strips through while the daemon runs.
+/// It needs no authorization, so it is only served on the local control address.
+/// Devices are addressed like Google addresses them, `0` being all strips together.
+pub struct Router;
+
+impl Routable for Router {
+    fn configure(config: &mut ServiceConfig) {
+        config.service(web::scope("/control")
+            .route("/{device}", web::get().to(get_device))
+            .route("/{device}", web::post().to(change_device))
+        );
+    }
+}
+
+/// The stored state of a device, as Google sees it
+#[derive(Debug, Serialize)]
+struct DeviceState {
+    on: bool,
+    /// In percent
+    brightness: u8,
+    color: Color,
+    /// The effect running on the strips, if any
+    effect: Option<EffectKind>,
+}
+
+/// A change to the state of a device. Everything not set is left as it is.
+#[derive(Debug, Deserialize)]
+struct StateChange {
+    on: Option<bool>,
+    /// In percent
+    brightness: Option<u8>,
+    color: Option<Color>,
+    effect: Option<Effect>,
+    #[serde(default)]
+    stop_effect: bool,
+    /// Fade the change in with this transition rather than the configured one.
+    /// Other changes, e.g. through Google, keep using the configured one.
+    transition: Option<Transition>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Effect {
+    kind: EffectKind,
+    #[serde(flatten)]
+    params: EffectParams,
+}
+
+#[instrument(skip_all)]
+async fn get_device(data: WebData, id: Path<String>) -> WebResult<Json<DeviceState>> {
+    let id = id.into_inner();
+    let strips = device_strips(&data, &id).ok_or(Error::NotFound)?;
+
+    let mut tx = data.pool.start_transaction(TxOpts::default())?;
+    let state = device_state(&mut tx, &id, &strips)?;
+    tx.commit()?;
+
+    Ok(Json(state))
+}
+
+#[instrument(skip_all)]
+async fn change_device(data: WebData, id: Path<String>, change: Json<StateChange>) -> WebResult<Json<DeviceState>> {
+    let id = id.into_inner();
+    let strips = device_strips(&data, &id).ok_or(Error::NotFound)?;
+    let affected = affected_devices(&data, &id);
+
+    let mut tx = data.pool.start_transaction(TxOpts::default())?;
+    let mut changes = StripChanges::default();
+    apply(&mut tx, &id, &affected, &mut changes, &change)?;
+    let mut state = device_state(&mut tx, &id, &strips)?;
+
+    // The strips are only changed once the new state is stored
+    tx.commit()?;
+    let strips = match change.transition {
+        Some(transition) => strips.with_transition(transition),
+        None => strips,
+    };
+    changes.apply(&strips)?;
+    state.effect = strips.effect();
+
+    // Flushing blocks until the first frame of the change is written, so it is kept off the server's thread.
+    // A transition is left to finish on its own.
+    web::block(move || strips.flush()).await??;
+
+    Ok(Json(state))
+}
+
+/// Apply a change the same way Google's commands are applied
+fn apply(tx: &mut Transaction, id: &str, affected: &[String], strips: &mut StripChanges, change: &StateChange) -> WebResult<()> {
+    if change.stop_effect {
+        state::stop_effect(tx, id, strips)?;
+    }
+    if let Some(color) = change.color {
+        state::change_color(tx, id, affected, strips, color.into())?;
+    }
+    if let Some(brightness) = change.brightness {
+        state::change_brightness(tx, id, affected, strips, brightness)?;
+    }
+    match change.on {
+        Some(true) => state::turn_on(tx, id, affected, strips)?,
+        Some(false) => state::turn_off(tx, affected, strips)?,
+        None => {},
+    }
+    if let Some(effect) = &change.effect {
+        state::start_effect(tx, affected, strips, effect.kind, &effect.params)?;
+    }
+
+    Ok(())
+}
+
+fn device_state(tx: &mut Transaction, id: &str, strips: &Strips) -> WebResult<DeviceState> {
+    Ok(DeviceState {
+        on: get_state(tx, id)?.unwrap_or(false),
+        brightness: get_brightness(tx, id)?.unwrap_or(100),
+        color: get_color(tx, id)?.map_or(Color::Rgb(Rgb::off()), Into::into),
+        effect: strips.effect(),
+    })
+}
@@ -4,6 +4,9 @@ use actix_web::web::Bytes;
 use serde::{Serialize, Deserialize};
 use tracing::instrument;
 use crate::authorization::Auth;
+use driver::{EffectKind, Strips};
+use mysql::Transaction;
+use crate::dal::device::{Color, get_brightness, get_color, get_state, Rgb};
 use crate::data::WebData;
 use crate::error::Error;
 use crate::WebResult;
@@ -68,7 +71,9 @@ mod sync {
     use serde::Serialize;
     use tracing::instrument;
     use crate::data::WebData;
-    use crate::routes::fulfillment::GenericResponse;
+    use crate::dal::device::GROUP_DEVICE;
+    use crate::routes::fulfillment::{COLOR_LOOP, GenericResponse, TEMPERATURE_MAX_K, TEMPERATURE_MIN_K};
+    use crate::state::device_ids;
     use crate::WebResult;
 
     #[derive(Debug, Serialize)]
@@ -92,7 +97,16 @@ mod sync {
     #[derive(Debug, Serialize)]
     #[serde(rename_all = "camelCase")]
     struct DeviceAttributes {
-        color_model: String
+        color_model: String,
+        color_temperature_range: ColorTemperatureRange,
+        supported_effects: Vec<String>,
+    }
+
+    #[derive(Debug, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct ColorTemperatureRange {
+        temperature_min_k: u16,
+        temperature_max_k: u16,
     }
 
     #[derive(Debug, Serialize)]
@@ -109,32 +123,47 @@ mod sync {
         name: String,
     }
 
+    fn device(id: String) -> Device {
+        // Individual strips are named after their name in the config
+        let name = if id == GROUP_DEVICE { "DeskLed".to_string() } else { id.clone() };
+
+        Device {
+            id,
+            device_type: "action.devices.types.LIGHT".to_string(),
+            traits: vec![
+                "action.devices.traits.OnOff".to_string(),
+                "action.devices.traits.ColorSetting".to_string(),
+                "action.devices.traits.Brightness".to_string(),
+                "action.devices.traits.LightEffects".to_string(),
+            ],
+            name: DeviceName {
+                name,
+            },
+            device_info: DeviceInfo {
+                manufacturer: "Array21 Development".to_string(),
+                model: "PiZero".to_string(),
+                hw_version: "0.1.0".to_string(),
+                sw_version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+            attributes: DeviceAttributes {
+                color_model: "rgb".to_string(),
+                color_temperature_range: ColorTemperatureRange {
+                    temperature_min_k: TEMPERATURE_MIN_K,
+                    temperature_max_k: TEMPERATURE_MAX_K,
+                },
+                supported_effects: vec![
+                    COLOR_LOOP.to_string(),
+                ],
+            }
+        }
+    }
+
     #[instrument(skip_all)]
     pub async fn sync(data: WebData, request_id: String) -> WebResult<String> {
         let payload = RequestPayload {
-            devices: vec![
-                Device {
-                    id: "0".to_string(),
-                    device_type: "action.devices.types.LIGHT".to_string(),
-                    traits: vec![
-                        "action.devices.traits.OnOff".to_string(),
-                        "action.devices.traits.ColorSetting".to_string(),
-                        "action.devices.traits.Brightness".to_string(),
-                    ],
-                    name: DeviceName {
-                        name: "DeskLed".to_string(),
-                    },
-                    device_info: DeviceInfo {
-                        manufacturer: "Array21 Development".to_string(),
-                        model: "PiZero".to_string(),
-                        hw_version: "0.1.0".to_string(),
-                        sw_version: env!("CARGO_PKG_VERSION").to_string(),
-                    },
-                    attributes: DeviceAttributes {
-                        color_model: "rgb".to_string(),
-                    }
-                }
-            ],
+            devices: device_ids(&data).into_iter()
+                .map(device)
+                .collect(),
             agent_user_id: data.config.login_username.clone(),
         };
 
@@ -148,13 +177,14 @@ mod sync {
 }
 
 mod query {
+    use std::collections::HashMap;
     use mysql::TxOpts;
     use serde::{Serialize, Deserialize};
     use tracing::instrument;
-    use crate::dal::device::{get_rgb, get_state, Rgb};
     use crate::data::WebData;
     use crate::error::Error;
-    use crate::routes::fulfillment::{DeviceColor, DeviceStatus, GenericRequest, GenericResponse};
+    use crate::routes::fulfillment::{device_status, DeviceStatus, GenericRequest, GenericResponse};
+    use crate::state::device_strips;
     use crate::WebResult;
 
     #[derive(Debug, Deserialize)]
@@ -169,60 +199,31 @@ mod query {
 
     #[derive(Debug, Serialize)]
     struct ResponsePayload {
-        devices: Vec<ResponseDevice>
-    }
-
-    #[derive(Debug, Serialize)]
-    struct ResponseDevice {
-        #[serde(rename = "0")]
-        zero: DeviceStatus
-    }
-
-    /// Returns an empty query response
-    fn query_return_empty(request_id: String) -> String {
-        let payload = GenericResponse {
-            request_id,
-            payload: ResponsePayload {
-                devices: Vec::default()
-            }
-        };
-
-        serde_json::to_string(&payload).unwrap()
+        devices: HashMap<String, DeviceStatus>
     }
 
     #[instrument(skip_all)]
     pub async fn query(data: WebData, payload: Vec<u8>) -> WebResult<String> {
         let payload: GenericRequest<RequestPayload> = serde_json::from_slice(&payload)?;
         let input = payload.inputs.first().ok_or(Error::BadRequest)?;
-        let device = match input.payload.devices.first() {
-            Some(x) => x,
-            None => return Ok(query_return_empty(payload.request_id))
-        };
-
-        if device.id.ne("0") {
-            return Ok(query_return_empty(payload.request_id));
-        }
 
         let mut tx = data.pool.start_transaction(TxOpts::default())?;
-        let rgb = get_rgb(&mut tx)?.unwrap_or(Rgb::off());
-        let on = get_state(&mut tx)?.unwrap_or(false);
+        let mut devices = HashMap::new();
+        for device in &input.payload.devices {
+            // Devices we don't know about are left out
+            let strips = match device_strips(&data, &device.id) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            devices.insert(device.id.clone(), device_status(&mut tx, &device.id, &strips)?);
+        }
         tx.commit()?;
 
         let payload = GenericResponse {
             request_id: payload.request_id,
             payload: ResponsePayload {
-                devices: vec! [
-                    ResponseDevice {
-                        zero: DeviceStatus {
-                            color: DeviceColor {
-                                spectrum_rgb: rgb.clone().into_spectrum_rgb()
-                            },
-                            brightness: on.then(|| rgb.get_brightness()).unwrap_or(0),
-                            on,
-                            online: true,
-                        }
-                    }
-                ]
+                devices
             }
         };
 
@@ -232,13 +233,15 @@ mod query {
 }
 
 mod execute {
-    use mysql::TxOpts;
+    use actix_web::web;
+    use driver::{EffectKind, EffectParams};
+    use mysql::{Transaction, TxOpts};
     use serde::{Serialize, Deserialize};
-    use tracing::instrument;
-    use crate::dal::device::{get_rgb, get_state, Rgb, set_rgb, set_state};
+    use tracing::{instrument, warn};
     use crate::data::WebData;
     use crate::error::Error;
-    use crate::routes::fulfillment::{DeviceColor, DeviceStatus, GenericRequest, GenericResponse};
+    use crate::routes::fulfillment::{active_light_effect, device_status, DeviceColor, DeviceStatus, GenericRequest, GenericResponse};
+    use crate::state::{self, affected_devices, device_strips, StripChanges};
     use crate::WebResult;
 
     #[derive(Debug, Deserialize)]
@@ -255,6 +258,7 @@ mod execute {
     #[derive(Debug, Deserialize)]
     struct Command {
         command: CommandType,
+        #[serde(default)]
         params: CommandParams,
     }
 
@@ -265,10 +269,14 @@ mod execute {
         #[serde(rename = "action.devices.commands.ColorAbsolute")]
         ColorAbsolute,
         #[serde(rename = "action.devices.commands.OnOff")]
-        OnOff
+        OnOff,
+        #[serde(rename = "action.devices.commands.ColorLoop")]
+        ColorLoop,
+        #[serde(rename = "action.devices.commands.StopEffect")]
+        StopEffect,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Default, Deserialize)]
     struct CommandParams {
         brightness: Option<u8>,
         color: Option<DeviceColor>,
@@ -286,9 +294,12 @@ mod execute {
     }
 
     #[derive(Debug, Serialize, Clone)]
+    #[serde(rename_all = "camelCase")]
     struct CommandResponse {
         ids: Vec<String>,
         status: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error_code: Option<String>,
         states: DeviceStatus
     }
 
@@ -313,107 +324,95 @@ mod execute {
         };
 
         let mut tx = data.pool.start_transaction(TxOpts::default())?;
+        let mut executed = Vec::new();
 
         for command in &input.payload.commands {
-            let has_zero = command.devices.iter()
-                .filter(|x| x.id.eq("0"))
-                .count() > 0;
-            if !has_zero {
-                return Ok(execute_empty_response(payload.request_id))
-            }
-
-            for exec in &command.execution {
-                match exec.command {
-                    CommandType::BrightnessAbsolute => {
-                        let brightness = exec.params.brightness.ok_or(Error::BadRequest)?;
-
-                        // Fetch the stored color and adjust its brightness
-                        let mut current = get_rgb(&mut tx)?.unwrap_or(Rgb::off());
-                        current.set_brightness(brightness);
-
-                        // Apply the color to the LEDs
-                        data.driver.send(current.clone()).await.expect("Channel closed");
-
-                        // store the new color
-                        set_rgb(&mut tx, current)?;
-                        // Store the ON/OFF state
-                        set_state(&mut tx, brightness > 0)?;
-                    },
-                    CommandType::ColorAbsolute => {
-                        let color = exec.params.color.as_ref().ok_or(Error::BadRequest)?.spectrum_rgb;
-                        let rgb = Rgb::from_spectrum_rgb(color);
-
-                        // If the device is not turned on, we don't want to
-                        // turn it on
-                        let on = get_state(&mut tx)?.unwrap_or(false);
-                        if on {
-                            data.driver.send(rgb.clone()).await.expect("Channel closed");
-                        }
-
-                        // Store the new color
-                        set_rgb(&mut tx, rgb)?;
-                    },
-                    CommandType::OnOff => {
-                        let on = exec.params.on.ok_or(Error::BadRequest)?;
-
-                        if on {
-                            // If the user wants to turn the LEDs on,
-                            // fetch the previous color, if it was black, make it white
-                            let prev_state = get_rgb(&mut tx)?.unwrap_or(Rgb::on());
-                            let prev_state = prev_state.is_off().then(|| Rgb::on()).unwrap_or(prev_state);
-
-                            // set the LEDs
-                            data.driver.send(prev_state.clone()).await.expect("Channel closed");
-                            // store the ON/OFF state
-                            set_state(&mut tx, true)?;
-                            // also store the color (in case it used to be black)
-                            set_rgb(&mut tx, prev_state)?;
-                        } else {
-                            // set the LEDs
-                            data.driver.send(Rgb::off()).await.expect("Channel closed");
-                            // Store the ON/OFF state
-                            set_state(&mut tx, false)?;
-
-                            // We dont set the color to black, this way
-                            // when the user turns the LEDs on again,
-                            // it'll restore the color/brightness they had set
-                            // before they turned it off.
-                        }
-                    }
+            for device in &command.devices {
+                // Devices we don't know about are left out
+                let strips = match device_strips(&data, &device.id) {
+                    Some(x) => x,
+                    None => continue,
+                };
+
+                let affected = affected_devices(&data, &device.id);
+                let mut changes = StripChanges::default();
+                for exec in &command.execution {
+                    execute_command(&mut tx, &device.id, &affected, &mut changes, exec)?;
                 }
+
+                let states = device_status(&mut tx, &device.id, &strips)?;
+                executed.push((device.id.clone(), strips, changes, states));
             }
         }
 
-        let rgb = get_rgb(&mut tx)?.unwrap_or(Rgb::off());
-        let on = get_state(&mut tx)?.unwrap_or(false);
+        // The strips are only changed once the new state is stored
+        tx.commit()?;
+        let executed = executed.into_iter()
+            .map(|(id, strips, changes, mut states)| {
+                changes.apply(&strips)?;
+                states.active_light_effect = active_light_effect(&strips);
+                Ok((id, strips, states))
+            })
+            .collect::<WebResult<Vec<_>>>()?;
+
+        // Wait for the strips to show the result, so Google hears about it if they can't.
+        // Flushing blocks until the frames are written, so it is kept off the server's threads.
+        let flushed = web::block(move || executed.into_iter()
+            .map(|(id, strips, states)| (strips.flush(), id, states))
+            .collect::<Vec<_>>()
+        ).await?;
+
+        let commands = flushed.into_iter()
+            .map(|(result, id, states)| {
+                let (status, error_code) = match result {
+                    Ok(_) => ("SUCCESS", None),
+                    Err(e) => {
+                        warn!("Failed to apply commands to device '{id}': {e}");
+                        ("ERROR", Some("hardError".to_string()))
+                    }
+                };
 
-        let status = CommandResponse {
-            ids: vec![
-                "0".to_string()
-            ],
-            status: "SUCCESS".to_string(),
-            states: DeviceStatus {
-                brightness: on.then(|| rgb.get_brightness()).unwrap_or(0),
-                on,
-                online: true,
-                color: DeviceColor {
-                    spectrum_rgb: rgb.into_spectrum_rgb()
+                CommandResponse {
+                    ids: vec![id],
+                    status: status.to_string(),
+                    error_code,
+                    states,
                 }
-            }
-        };
-
-        tx.commit()?;
+            })
+            .collect();
 
         let payload = GenericResponse {
             request_id: payload.request_id,
             payload: ResponsePayload {
-                commands: vec![status; input.payload.commands.len()]
+                commands
             }
         };
 
         let res = serde_json::to_string(&payload)?;
         Ok(res)
     }
+
+    /// Execute a command on the strips of a device
+    fn execute_command(tx: &mut Transaction, id: &str, affected: &[String], strips: &mut StripChanges, exec: &Command) -> WebResult<()> {
+        match exec.command {
+            CommandType::BrightnessAbsolute => {
+                let brightness = exec.params.brightness.ok_or(Error::BadRequest)?;
+                state::change_brightness(tx, id, affected, strips, brightness)
+            },
+            CommandType::ColorAbsolute => {
+                let color = exec.params.color.as_ref()
+                    .and_then(DeviceColor::to_color)
+                    .ok_or(Error::BadRequest)?;
+                state::change_color(tx, id, affected, strips, color)
+            },
+            CommandType::OnOff => match exec.params.on.ok_or(Error::BadRequest)? {
+                true => state::turn_on(tx, id, affected, strips),
+                false => state::turn_off(tx, affected, strips),
+            },
+            CommandType::ColorLoop => state::start_effect(tx, affected, strips, EffectKind::Rainbow, &EffectParams::default()),
+            CommandType::StopEffect => state::stop_effect(tx, id, strips),
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -436,15 +435,76 @@ struct GenericInput<T: Debug> {
 }
 
 #[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
 struct DeviceStatus {
     on: bool,
     online: bool,
     brightness: u8,
-    color: DeviceColor
+    color: DeviceColor,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    active_light_effect: Option<String>,
+}
+
+/// The only effect Google knows about which we support
+const COLOR_LOOP: &str = "colorLoop";
+
+/// The color temperatures Google lets users pick from
+const TEMPERATURE_MIN_K: u16 = 2000;
+const TEMPERATURE_MAX_K: u16 = 9000;
+
+/// The stored state of a device, and the effect running on its strips
+fn device_status(tx: &mut Transaction, id: &str, strips: &Strips) -> WebResult<DeviceStatus> {
+    let color = get_color(tx, id)?.unwrap_or(Color::Rgb(Rgb::off()));
+    let brightness = get_brightness(tx, id)?.unwrap_or(100);
+    let on = get_state(tx, id)?.unwrap_or(false);
+
+    Ok(DeviceStatus {
+        on,
+        online: true,
+        brightness: if on { brightness } else { 0 },
+        color: DeviceColor::from_color(&color),
+        active_light_effect: active_light_effect(strips),
+    })
 }
 
+/// The Google name of the effect currently running, if any
+fn active_light_effect(strips: &Strips) -> Option<String> {
+    match strips.effect() {
+        Some(EffectKind::Rainbow) => Some(COLOR_LOOP.to_string()),
+        _ => None,
+    }
+}
+
+/// Either a color or a color temperature
 #[derive(Debug, Deserialize, Serialize, Clone)]
 struct DeviceColor {
-    #[serde(rename = "spectrumRGB")]
-    spectrum_rgb: i32
+    #[serde(rename = "spectrumRGB", skip_serializing_if = "Option::is_none")]
+    spectrum_rgb: Option<i32>,
+    /// Google sets it as `temperature`, but queries it as `temperatureK`
+    #[serde(rename(serialize = "temperatureK", deserialize = "temperature"), alias = "temperatureK", skip_serializing_if = "Option::is_none")]
+    temperature: Option<u16>,
+}
+
+impl DeviceColor {
+    fn from_color(color: &Color) -> Self {
+        match color {
+            Color::Rgb(rgb) => Self {
+                spectrum_rgb: Some(rgb.into_spectrum_rgb()),
+                temperature: None,
+            },
+            Color::Kelvin(kelvin) => Self {
+                spectrum_rgb: None,
+                temperature: Some(*kelvin),
+            }
+        }
+    }
+
+    /// `None` if neither a color nor a temperature is set
+    fn to_color(&self) -> Option<Color> {
+        match (self.temperature, self.spectrum_rgb) {
+            (Some(kelvin), _) => Some(Color::Kelvin(kelvin)),
+            (None, Some(spectrum_rgb)) => Some(Color::Rgb(Rgb::from_spectrum_rgb(spectrum_rgb))),
+            (None, None) => None,
+        }
+    }
 }
\ No newline at end of file
@@ -4,6 +4,7 @@ use crate::routable::Routable;
 
 mod oauth2;
 mod fulfillment;
+pub mod control;
 
 pub struct Router;
 
@@ -0,0 +1,154 @@
+//! Changes to the state of devices, shared by Google and the control API.
+//! Every change is stored and applied to the strips, so both always agree on the state.
+//! The changes to the strips are collected in [StripChanges], to be applied once the state is committed.
+
+use driver::{EffectKind, EffectParams, Strips};
+use mysql::Transaction;
+use crate::dal::device::{Color, get_brightness, get_color, get_state, GROUP_DEVICE, Rgb, set_brightness, set_color, set_state};
+use crate::data::WebData;
+use crate::WebResult;
+
+/// The devices: all strips together, followed by
+/// every individual strip if there is more than one
+pub fn device_ids(data: &WebData) -> Vec<String> {
+    let mut ids = vec![GROUP_DEVICE.to_string()];
+    if data.driver.len() > 1 {
+        ids.extend(data.driver.names().map(str::to_string));
+    }
+
+    ids
+}
+
+/// The strips controlled by a device, `None` if there is no such device
+pub fn device_strips(data: &WebData, id: &str) -> Option<Strips> {
+    if id == GROUP_DEVICE {
+        return Some(data.driver.clone());
+    }
+
+    // Individual strips are only exposed if there is more than one
+    if data.driver.len() > 1 {
+        data.driver.only(id).ok()
+    } else {
+        None
+    }
+}
+
+/// The devices whose stored state changes along with this device.
+/// Changing all strips at once also changes every individual strip.
+pub fn affected_devices(data: &WebData, id: &str) -> Vec<String> {
+    if id == GROUP_DEVICE {
+        device_ids(data)
+    } else {
+        vec![id.to_string()]
+    }
+}
+
+/// Changes to the strips, in the order they were made
+#[derive(Debug, Default)]
+pub struct StripChanges(Vec<StripChange>);
+
+#[derive(Debug)]
+enum StripChange {
+    Brightness(u8),
+    Color(Color),
+    StartEffect(EffectKind, EffectParams),
+    StopEffect,
+}
+
+impl StripChanges {
+    fn push(&mut self, change: StripChange) {
+        self.0.push(change);
+    }
+
+    /// Apply the changes to the strips. This only queues them up for the strips' engines,
+    /// use [Strips::flush] to wait until they are shown.
+    pub fn apply(self, strips: &Strips) -> driver::Result<()> {
+        self.0.into_iter().try_for_each(|change| match change {
+            // The brightness is applied by the driver rather than to the color,
+            // so dim colors keep their hue
+            StripChange::Brightness(brightness) => strips.set_brightness(brightness as f32 / 100.0),
+            StripChange::Color(color) => strips.set_color(color.into()),
+            StripChange::StartEffect(kind, params) => strips.start_effect(kind, &params),
+            StripChange::StopEffect => strips.stop_effect(),
+        })
+    }
+}
+
+/// Store state for the device and every device affected by it
+fn store<F: FnMut(&mut Transaction, &str) -> WebResult<()>>(tx: &mut Transaction, affected: &[String], mut f: F) -> WebResult<()> {
+    affected.iter().try_for_each(|id| f(tx, id))
+}
+
+/// Set the brightness in percent, turning the device on unless it is 0.
+/// The stored state of the device itself is read, the state of all affected devices is updated,
+/// as for every change below.
+pub fn change_brightness(tx: &mut Transaction, id: &str, affected: &[String], strips: &mut StripChanges, brightness: u8) -> WebResult<()> {
+    let current = get_color(tx, id)?.unwrap_or(Color::Rgb(Rgb::off()));
+    strips.push(StripChange::Brightness(brightness));
+    strips.push(StripChange::Color(current));
+
+    // store the new brightness
+    store(tx, affected, |tx, id| set_brightness(tx, id, brightness))?;
+    // Store the ON/OFF state
+    store(tx, affected, |tx, id| set_state(tx, id, brightness > 0))
+}
+
+/// Set the color, which is only shown if the device is on
+pub fn change_color(tx: &mut Transaction, id: &str, affected: &[String], strips: &mut StripChanges, color: Color) -> WebResult<()> {
+    // If the device is not turned on, we don't want to
+    // turn it on
+    let on = get_state(tx, id)?.unwrap_or(false);
+    if on {
+        strips.push(StripChange::Color(color.clone()));
+    }
+
+    // Store the new color
+    store(tx, affected, |tx, id| set_color(tx, id, color.clone()))
+}
+
+pub fn turn_on(tx: &mut Transaction, id: &str, affected: &[String], strips: &mut StripChanges) -> WebResult<()> {
+    // If the user wants to turn the LEDs on,
+    // fetch the previous color, if it was black, make it white
+    let prev_state = get_color(tx, id)?.unwrap_or(Color::Rgb(Rgb::on()));
+    let prev_state = prev_state.is_off().then(|| Color::Rgb(Rgb::on())).unwrap_or(prev_state);
+    // the same goes for a brightness of 0
+    let brightness = get_brightness(tx, id)?.filter(|x| *x > 0).unwrap_or(100);
+
+    // set the LEDs
+    strips.push(StripChange::Brightness(brightness));
+    strips.push(StripChange::Color(prev_state.clone()));
+    // store the ON/OFF state
+    store(tx, affected, |tx, id| set_state(tx, id, true))?;
+    // also store the color and brightness (in case it used to be black)
+    store(tx, affected, |tx, id| set_color(tx, id, prev_state.clone()))?;
+    store(tx, affected, |tx, id| set_brightness(tx, id, brightness))
+}
+
+pub fn turn_off(tx: &mut Transaction, affected: &[String], strips: &mut StripChanges) -> WebResult<()> {
+    // set the LEDs
+    strips.push(StripChange::Color(Color::Rgb(Rgb::off())));
+
+    // We dont set the color to black, this way
+    // when the user turns the LEDs on again,
+    // it'll restore the color/brightness they had set
+    // before they turned it off.
+    // So only the ON/OFF state is stored
+    store(tx, affected, |tx, id| set_state(tx, id, false))
+}
+
+pub fn start_effect(tx: &mut Transaction, affected: &[String], strips: &mut StripChanges, kind: EffectKind, params: &EffectParams) -> WebResult<()> {
+    strips.push(StripChange::StartEffect(kind, params.clone()));
+    store(tx, affected, |tx, id| set_state(tx, id, true))
+}
+
+pub fn stop_effect(tx: &mut Transaction, id: &str, strips: &mut StripChanges) -> WebResult<()> {
+    strips.push(StripChange::StopEffect);
+
+    // Go back to the color from before the effect was started
+    let on = get_state(tx, id)?.unwrap_or(false);
+    let color = get_color(tx, id)?.unwrap_or(Color::Rgb(Rgb::off()));
+    let color = if on { color } else { Color::Rgb(Rgb::off()) };
+    strips.push(StripChange::Color(color));
+
+    Ok(())
+}